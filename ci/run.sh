@@ -75,6 +75,8 @@ if [ "$NOSTD" != "1" ]; then
     cargo_test "${STD_DETECT} --no-default-features --features=std_detect_file_io"
     cargo_test "${STD_DETECT} --no-default-features --features=std_detect_dlsym_getauxval"
     cargo_test "${STD_DETECT} --no-default-features --features=std_detect_dlsym_getauxval,std_detect_file_io"
+    cargo_test "${STD_DETECT} --features=std_detect_thread_local_cache"
+    cargo_test "${STD_DETECT} --features=std_detect_stack_auxv"
 
     cargo_test "${STDARCH_EXAMPLES}"
     cargo_test "${STDARCH_EXAMPLES} --release"
@@ -86,6 +88,7 @@ case ${TARGET} in
         export STDARCH_DISABLE_ASSERT_INSTR=1
         export RUSTFLAGS="${RUSTFLAGS} -C target-feature=+avx"
         cargo_test "--release"
+        cargo_test "${STD_DETECT} --release --features=std_detect_env_override"
         ;;
     # FIXME: don't build anymore
     #mips-*gnu* | mipsel-*gnu*)
@@ -35,4 +35,48 @@ features! {
     /// Vector Dot-Product (ASIMDDP)
     @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] tme: "tme";
     /// Transactional Memory Extensions (TME)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] lse2: "lse2";
+    /// Large System Extension version 2 (LSE2): unaligned single-copy atomicity
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] fhm: "fhm";
+    /// Half-float fused multiply-add to single-float (FHM)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] sha512: "sha512";
+    /// SHA1 + SHA2 + SHA512
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] sha3: "sha3";
+    /// SHA1 + SHA2 + SHA512 + SHA3
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] sm4: "sm4";
+    /// SM3 + SM4
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] fcma: "fcma";
+    /// Floating point complex number support (FCMA)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] jsconv: "jsconv";
+    /// JavaScript conversion from double to int32 (JSCVT)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] dpb: "dpb";
+    /// DC CVAP: clean data cache to the point of persistence (DPB)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] dpb2: "dpb2";
+    /// DC CVADP: clean data cache to the point of deep persistence (DPB2)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] flagm: "flagm";
+    /// Flag manipulation instructions (FlagM)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] flagm2: "flagm2";
+    /// Flag manipulation instructions version 2 (FlagM2)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] aes: "aes";
+    /// Advanced Encryption Standard (AES)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] ecv: "ecv";
+    /// Enhanced Counter Virtualization (ECV)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] rprfm: "rprfm";
+    /// Range Prefetch Memory hint (RPRFM)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] sve2: "sve2";
+    /// Scalable Vector Extension version 2 (SVE2)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] sve2p1: "sve2p1";
+    /// Scalable Vector Extension version 2.1 (SVE2p1)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] sme: "sme";
+    /// Scalable Matrix Extension (SME)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] sme2: "sme2";
+    /// Scalable Matrix Extension version 2 (SME2)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] sme2p1: "sme2p1";
+    /// Scalable Matrix Extension version 2.1 (SME2p1)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] faminmax: "faminmax";
+    /// Floating point absolute maximum and minimum (FAMINMAX)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] lut: "lut";
+    /// Lookup table instructions (LUT)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] predres: "predres";
+    /// Prediction invalidation instructions: CFP, DVP, and CPP RCTX (SPECRES)
 }
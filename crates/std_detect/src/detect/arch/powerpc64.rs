@@ -12,4 +12,16 @@ features! {
     /// VSX
     @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] power8: "power8";
     /// Power8
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] power9: "power9";
+    /// Power9
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] power10: "power10";
+    /// Power10
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] htm: "htm";
+    /// HTM (Hardware Transactional Memory)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] htm_nosc: "htm-nosc";
+    /// HTM without the transaction being suspended on system calls
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] ebb: "ebb";
+    /// EBB (Event-Based Branches)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] isel: "isel";
+    /// ISEL (Integer Select)
 }
@@ -88,8 +88,24 @@ features! {
     /// * `"xsaves"`
     /// * `"xsavec"`
     /// * `"cmpxchg16b"`
+    /// * `"lahfsahf"`
+    /// * `"movbe"`
+    /// * `"osxsave"`
+    /// * `"gfni"`
+    /// * `"vaes"`
+    /// * `"vpclmulqdq"`
+    /// * `"avx5124vnniw"`
+    /// * `"avx5124fmaps"`
+    /// * `"avx10.1-256"`
+    /// * `"avx10.1-512"`
+    /// * `"cpb"`
+    /// * `"rdtscp"`
+    /// * `"x87"`
+    /// * `"clflushopt"`
+    /// * `"clwb"`
     /// * `"adx"`
     /// * `"rtm"`
+    /// * `"movrs"`
     ///
     /// [docs]: https://software.intel.com/sites/landingpage/IntrinsicsGuide
     #[stable(feature = "simd_x86", since = "1.27.0")]
@@ -193,4 +209,36 @@ features! {
     /// ADX, Intel ADX (Multi-Precision Add-Carry Instruction Extensions)
     @FEATURE: #[stable(feature = "simd_x86", since = "1.27.0")] rtm: "rtm";
     /// RTM, Intel (Restricted Transactional Memory)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] lahfsahf: "lahfsahf";
+    /// LAHF/SAHF (`LAHF` and `SAHF` instructions in 64-bit mode)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] movbe: "movbe";
+    /// MOVBE (Move Data After Swapping Bytes)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] osxsave: "osxsave";
+    /// OSXSAVE (the OS has enabled `XGETBV` and the XSAVE feature set)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] gfni: "gfni";
+    /// GFNI (Galois Field New Instructions, the VEX encodings also need `avx`)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] vaes: "vaes";
+    /// VAES (256-bit vector AES instructions, VEX encoded)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] vpclmulqdq: "vpclmulqdq";
+    /// VPCLMULQDQ (256-bit vector carry-less multiplication, VEX encoded)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] avx5124vnniw: "avx5124vnniw";
+    /// AVX-512 4VNNIW (Vector Neural Network Instructions Word variable precision, Knights Mill)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] avx5124fmaps: "avx5124fmaps";
+    /// AVX-512 4FMAPS (Fused Multiply Accumulation Packed Single precision, Knights Mill)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] avx10_1_256: "avx10.1-256";
+    /// AVX10.1 with 128-bit and 256-bit vectors
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] avx10_1_512: "avx10.1-512";
+    /// AVX10.1 with 128-bit, 256-bit, and 512-bit vectors
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] cpb: "cpb";
+    /// CPB (AMD Core Performance Boost)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] rdtscp: "rdtscp";
+    /// RDTSCP (Read Time-Stamp Counter and Processor ID)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] x87: "x87";
+    /// x87 FPU (Floating-Point Unit on chip)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] clflushopt: "clflushopt";
+    /// CLFLUSHOPT (Flush Cache Line Optimized)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] clwb: "clwb";
+    /// CLWB (Cache Line Write Back)
+    @FEATURE: #[unstable(feature = "stdsimd", issue = "27731")] movrs: "movrs";
+    /// MOVRS (Move and prefetch with a read-shared hint: `MOVRS` and `PREFETCHRST2`)
 }
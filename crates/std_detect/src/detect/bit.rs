@@ -1,9 +1,33 @@
 //! Bit manipulation utilities.
 
+use crate::detect::{cache, Feature};
+
 /// Tests the `bit` of `x`.
 #[allow(dead_code)]
 #[inline]
 pub(crate) fn test(x: usize, bit: u32) -> bool {
-    debug_assert!(bit < 32, "bit index out-of-bounds");
+    debug_assert!(
+        (bit as usize) < core::mem::size_of::<usize>() * 8,
+        "bit index out-of-bounds"
+    );
     x & (1 << bit) != 0
 }
+
+/// Enables the features of `table` whose bit is set in the `bits` bitfield,
+/// e.g., `AT_HWCAP`.
+///
+/// Each entry of `table` is the index of a bit in `bits` and the feature that
+/// it indicates.
+#[allow(dead_code)]
+#[inline]
+pub(crate) fn enable_features(
+    value: &mut cache::Initializer,
+    bits: usize,
+    table: &[(u32, Feature)],
+) {
+    for &(bit, f) in table {
+        if test(bits, bit) {
+            value.set(f as u32);
+        }
+    }
+}
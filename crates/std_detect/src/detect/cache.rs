@@ -5,35 +5,37 @@
 
 use core::sync::atomic::Ordering;
 
-use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicBool, AtomicUsize};
 
 /// Sets the `bit` of `x`.
 #[inline]
-const fn set_bit(x: u64, bit: u32) -> u64 {
+const fn set_bit(x: u128, bit: u32) -> u128 {
     x | 1 << bit
 }
 
 /// Tests the `bit` of `x`.
 #[inline]
-const fn test_bit(x: u64, bit: u32) -> bool {
+const fn test_bit(x: u128, bit: u32) -> bool {
     x & (1 << bit) != 0
 }
 
 /// Unset the `bit of `x`.
 #[inline]
-const fn unset_bit(x: u64, bit: u32) -> u64 {
+const fn unset_bit(x: u128, bit: u32) -> u128 {
     x & !(1 << bit)
 }
 
 /// Maximum number of features that can be cached.
-const CACHE_CAPACITY: u32 = 62;
+const CACHE_CAPACITY: u32 = 93;
 
 /// This type is used to initialize the cache
-#[derive(Copy, Clone)]
-pub(crate) struct Initializer(u64);
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub struct Initializer(u128);
 
 #[allow(clippy::use_self)]
 impl Default for Initializer {
+    #[inline]
     fn default() -> Self {
         Initializer(0)
     }
@@ -76,8 +78,26 @@ impl Initializer {
 }
 
 /// This global variable is a cache of the features supported by the CPU.
-// Note: on x64, we only use the first slot
-static CACHE: [Cache; 2] = [Cache::uninitialized(), Cache::uninitialized()];
+// Note: on x64, we only use the first two slots
+static CACHE: [Cache; 3] = [
+    Cache::uninitialized(),
+    Cache::uninitialized(),
+    Cache::uninitialized(),
+];
+
+/// A per-thread copy of `CACHE`, populated on the first read of each thread
+/// after `CACHE` has been initialized, so that subsequent reads are plain
+/// loads instead of atomic loads.
+///
+/// This uses `#[thread_local]` because `std::thread_local!` is not available
+/// in `no_std`, which is why it is behind a cargo feature.
+#[cfg(feature = "std_detect_thread_local_cache")]
+#[thread_local]
+static THREAD_CACHE: [core::cell::Cell<usize>; 3] = [
+    core::cell::Cell::new(0),
+    core::cell::Cell::new(0),
+    core::cell::Cell::new(0),
+];
 
 /// Feature cache with capacity for `size_of::<usize::MAX>() * 8 - 1` features.
 ///
@@ -107,10 +127,16 @@ impl Cache {
         if cached == 0 {
             None
         } else {
-            Some(test_bit(cached as u64, bit))
+            Some(test_bit(cached as u128, bit))
         }
     }
 
+    /// Has the cache been initialized?
+    #[inline]
+    fn is_initialized(&self) -> bool {
+        self.0.load(Ordering::Relaxed) != 0
+    }
+
     /// Initializes the cache.
     #[inline]
     fn initialize(&self, value: usize) -> usize {
@@ -123,8 +149,10 @@ impl Cache {
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "std_detect_env_override")] {
+        /// Disables the features listed in the `RUST_STD_DETECT_UNSTABLE`
+        /// environment variable.
         #[inline]
-        fn initialize(mut value: Initializer) -> Initializer {
+        fn disable_env_features(mut value: Initializer) -> Initializer {
             let env = unsafe {
                 libc::getenv(b"RUST_STD_DETECT_UNSTABLE\0".as_ptr() as *const libc::c_char)
             };
@@ -137,22 +165,28 @@ cfg_if::cfg_if! {
                     }
                 }
             }
-            do_initialize(value);
             value
         }
     } else {
         #[inline]
-        fn initialize(value: Initializer) -> Initializer {
-            do_initialize(value);
+        fn disable_env_features(value: Initializer) -> Initializer {
             value
         }
     }
 }
 
+#[inline]
+fn initialize(value: Initializer) -> Initializer {
+    let value = disable_env_features(value);
+    do_initialize(value);
+    value
+}
+
 #[inline]
 fn do_initialize(value: Initializer) {
     CACHE[0].initialize((value.0) as usize & Cache::MASK);
     CACHE[1].initialize((value.0 >> Cache::CAPACITY) as usize & Cache::MASK);
+    CACHE[2].initialize((value.0 >> (2 * Cache::CAPACITY)) as usize & Cache::MASK);
 }
 
 // We only have to detect features once, and it's fairly costly, so hint to LLVM
@@ -166,14 +200,336 @@ fn do_initialize(value: Initializer) {
 // cache again.
 #[cold]
 fn detect_and_initialize() -> Initializer {
-    initialize(super::os::detect_features())
+    let mut value = super::os::detect_features();
+    super::expand_implied_features(&mut value, super::IMPLIED_FEATURES);
+    if !claim_initialization() {
+        // Another thread is initializing the cache, possibly with an
+        // installed snapshot, which is only a few stores away:
+        while !__detection_initialized() {
+            core::hint::spin_loop();
+        }
+        return cached();
+    }
+    SOURCE.store(DETECTED_SOURCE.load(Ordering::Relaxed), Ordering::Relaxed);
+    let value = initialize(value);
+    notify_observer(&value);
+    value
+}
+
+/// Whether a thread has started to initialize the cache.
+static INITIALIZATION_CLAIMED: AtomicBool = AtomicBool::new(false);
+
+/// Returns `true` if the caller is the one thread that may initialize the
+/// cache, either with the detected features or with an installed snapshot.
+#[inline]
+fn claim_initialization() -> bool {
+    INITIALIZATION_CLAIMED
+        .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
+        .is_ok()
+}
+
+/// The function registered with `__set_detection_observer` as a `usize`, or
+/// `0` if no function has been registered.
+static OBSERVER: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether the registered observer has already been invoked.
+static OBSERVER_NOTIFIED: AtomicBool = AtomicBool::new(false);
+
+/// Registers `f` to be invoked with the detected features once run-time
+/// feature detection completes.
+///
+/// The observer must be registered before the first run-time feature
+/// detection, and is invoked at most once, even if several threads race to
+/// perform the detection. It is invoked after the cache has been initialized,
+/// so it can, e.g., call [`features`](super::features) to log the result. The
+/// `Initializer` that it is invoked with can be converted into a
+/// [`FeatureSnapshot`] with `FeatureSnapshot::from(*value)`, e.g., to forward
+/// the detected features to a sandbox.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __set_detection_observer(f: fn(&Initializer)) {
+    OBSERVER.store(f as usize, Ordering::Relaxed);
+}
+
+/// Invokes the registered observer with `value`, unless it has already been
+/// invoked.
+#[inline]
+fn notify_observer(value: &Initializer) {
+    let observer = OBSERVER.load(Ordering::Relaxed);
+    if observer != 0 && !OBSERVER_NOTIFIED.swap(true, Ordering::Relaxed) {
+        // This is safe because only `fn(&Initializer)`s are stored in
+        // `OBSERVER`.
+        let observer: fn(&Initializer) = unsafe { core::mem::transmute(observer) };
+        observer(value);
+    }
+}
+
+/// The mechanism that run-time feature detection used to detect a feature,
+/// see [`__detection_source`](super::__detection_source).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub enum DetectionSource {
+    /// The feature is not detected.
+    NotDetected,
+    /// The feature is enabled at compile-time, but has not been detected at
+    /// run-time, e.g., because it has been disabled with the
+    /// `RUST_STD_DETECT_UNSTABLE` environment variable.
+    CompileTime,
+    /// The `cpuid` instruction on x86.
+    Cpuid,
+    /// The `AT_HWCAP` and `AT_HWCAP2` entries of the ELF auxiliary vector.
+    Hwcap,
+    /// `/proc/cpuinfo` on Linux.
+    CpuInfo,
+    /// The ID registers, read with the `mrs` instruction that the OS
+    /// emulates.
+    Mrs,
+    /// `sysctl`s, on macOS, OpenBSD, and NetBSD.
+    Sysctl,
+    /// `IsProcessorFeaturePresent` on Windows.
+    IsProcessorFeaturePresent,
+    /// A [`FeatureSnapshot`] installed with [`__install_feature_snapshot`].
+    Snapshot,
+}
+
+impl DetectionSource {
+    /// The variants, indexed by their discriminant.
+    const ALL: [DetectionSource; 9] = [
+        DetectionSource::NotDetected,
+        DetectionSource::CompileTime,
+        DetectionSource::Cpuid,
+        DetectionSource::Hwcap,
+        DetectionSource::CpuInfo,
+        DetectionSource::Mrs,
+        DetectionSource::Sysctl,
+        DetectionSource::IsProcessorFeaturePresent,
+        DetectionSource::Snapshot,
+    ];
+}
+
+/// The `DetectionSource` of the features in the cache as a `usize`, or `0`
+/// (`NotDetected`) if no mechanism has succeeded.
+static SOURCE: AtomicUsize = AtomicUsize::new(0);
+
+/// The `DetectionSource` of the last call to `os::detect_features` as a
+/// `usize`. It is only copied to `SOURCE` when the cache is initialized with
+/// the result of that call, so that detecting the features again in
+/// `__diff_from_snapshot` leaves the source of the cached features alone.
+static DETECTED_SOURCE: AtomicUsize = AtomicUsize::new(0);
+
+/// Records that `os::detect_features` detected the features with `source`.
+///
+/// Every implementation of `os::detect_features` reads all of the features
+/// with a single mechanism, so this is called once the mechanism that is
+/// used has been chosen. Implied features are reported with the source of
+/// the features that imply them.
+#[inline]
+pub(crate) fn set_detection_source(source: DetectionSource) {
+    DETECTED_SOURCE.store(source as usize, Ordering::Relaxed);
+}
+
+/// Returns the `DetectionSource` of the features in the cache.
+#[inline]
+pub(crate) fn detection_source() -> DetectionSource {
+    DetectionSource::ALL[SOURCE.load(Ordering::Relaxed)]
+}
+
+/// Returns `true` if run-time feature detection has already been performed
+/// and its results have been cached.
+///
+/// This does not perform run-time feature detection itself.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __detection_initialized() -> bool {
+    CACHE.iter().all(Cache::is_initialized)
+}
+
+/// Performs run-time feature detection and caches its results, unless this
+/// has already happened.
+///
+/// After this returns, the `is_{arch}_feature_detected!` macros only read the
+/// cache. This allows paying the cost of run-time feature detection, which
+/// might involve system calls, up-front, e.g., before spawning real-time
+/// threads.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __initialize_detection() {
+    if !__detection_initialized() {
+        detect_and_initialize();
+    }
+}
+
+/// Returns the cached features, performing run-time feature detection if this
+/// has not happened yet.
+fn detected() -> Initializer {
+    if !__detection_initialized() {
+        return detect_and_initialize();
+    }
+    cached()
+}
+
+/// Returns the features in the cache, which must have been initialized.
+fn cached() -> Initializer {
+    let mut value = 0;
+    for (i, cache) in CACHE.iter().enumerate() {
+        let slot = cache.0.load(Ordering::Relaxed) & Cache::MASK;
+        value |= (slot as u128) << (i as u32 * Cache::CAPACITY);
+    }
+    Initializer(value)
+}
+
+/// A plain-old-data snapshot of the detected features.
+///
+/// It can be converted to and from bytes, e.g., to pass the features detected
+/// by a host process across an FFI boundary to a sandboxed plugin. The bytes
+/// are only meaningful to the same version of this crate on the same target.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub struct FeatureSnapshot {
+    bits: u128,
+}
+
+#[unstable(feature = "stdsimd", issue = "27731")]
+impl FeatureSnapshot {
+    /// The size of the byte representation of a snapshot.
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub const SIZE: usize = core::mem::size_of::<u128>();
+
+    /// Takes a snapshot of the features detected on the host, performing
+    /// run-time feature detection if this has not happened yet.
+    #[inline]
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub fn current() -> Self {
+        Self::from(detected())
+    }
+
+    /// Returns an `Iterator<Item=(&'static str, bool)>` like
+    /// [`features`](super::features), but `Item.1` is `true` if the feature
+    /// is enabled in the snapshot.
+    #[inline]
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub fn features(&self) -> impl Iterator<Item = (&'static str, bool)> {
+        // Bits beyond the capacity of the cache do not correspond to features:
+        let value = Initializer(self.bits & ((1 << CACHE_CAPACITY) - 1));
+        super::feature_states(move |f| value.test(f as u32))
+    }
+
+    /// Returns the little-endian byte representation of the snapshot.
+    #[inline]
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        self.bits.to_le_bytes()
+    }
+
+    /// Creates a snapshot from its byte representation, as returned by
+    /// [`to_bytes`](Self::to_bytes).
+    #[inline]
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        Self {
+            bits: u128::from_le_bytes(bytes),
+        }
+    }
+}
+
+#[unstable(feature = "stdsimd", issue = "27731")]
+impl From<Initializer> for FeatureSnapshot {
+    #[inline]
+    fn from(value: Initializer) -> Self {
+        Self { bits: value.0 }
+    }
+}
+
+/// Initializes the cache with the features of `snap` instead of performing
+/// run-time feature detection.
+///
+/// This is meant for sandboxes and plugins that cannot query the hardware
+/// themselves and trust the features detected by their host, see
+/// [`FeatureSnapshot`]. The snapshot must be installed before the first
+/// run-time feature detection; if the cache has already been initialized, or
+/// another thread is initializing it, this does nothing. Like detected
+/// features, the features listed in `RUST_STD_DETECT_UNSTABLE` are disabled.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __install_feature_snapshot(snap: FeatureSnapshot) {
+    if claim_initialization() {
+        // Bits beyond the capacity of the cache do not correspond to features:
+        let mut value = Initializer(snap.bits & ((1 << CACHE_CAPACITY) - 1));
+        super::expand_implied_features(&mut value, super::IMPLIED_FEATURES);
+        SOURCE.store(DetectionSource::Snapshot as usize, Ordering::Relaxed);
+        let value = initialize(value);
+        notify_observer(&value);
+    }
+}
+
+/// The features gained and lost between two [`FeatureSnapshot`]s, see
+/// [`__diff_from_snapshot`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub struct FeatureDiff {
+    gained: u128,
+    lost: u128,
+}
+
+#[unstable(feature = "stdsimd", issue = "27731")]
+impl FeatureDiff {
+    /// Compares the features of `old` with those of `new`.
+    #[inline]
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub fn between(old: &FeatureSnapshot, new: &FeatureSnapshot) -> Self {
+        // Bits beyond the capacity of the cache do not correspond to features:
+        let mask = (1 << CACHE_CAPACITY) - 1;
+        Self {
+            gained: new.bits & !old.bits & mask,
+            lost: old.bits & !new.bits & mask,
+        }
+    }
+
+    /// Returns `true` if no feature has been gained or lost.
+    #[inline]
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub fn is_empty(&self) -> bool {
+        self.gained == 0 && self.lost == 0
+    }
+
+    /// Returns the names of the features that are only enabled in the new
+    /// snapshot, in the same order as [`features`](super::features).
+    #[inline]
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub fn gained(&self) -> impl Iterator<Item = &'static str> {
+        super::feature_names(Initializer(self.gained))
+    }
+
+    /// Returns the names of the features that are only enabled in the old
+    /// snapshot, in the same order as [`features`](super::features).
+    #[inline]
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub fn lost(&self) -> impl Iterator<Item = &'static str> {
+        super::feature_names(Initializer(self.lost))
+    }
+}
+
+/// Performs run-time feature detection again and compares its result with
+/// `old`, e.g., to find out whether a virtual machine has been live-migrated
+/// to a host with a different CPU.
+///
+/// The cache is not updated, so the `is_{arch}_feature_detected!` macros keep
+/// reporting the features detected the first time.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __diff_from_snapshot(old: &FeatureSnapshot) -> FeatureDiff {
+    let mut value = super::os::detect_features();
+    super::expand_implied_features(&mut value, super::IMPLIED_FEATURES);
+    let value = disable_env_features(value);
+    FeatureDiff::between(old, &FeatureSnapshot::from(value))
 }
 
 /// Tests the `bit` of the storage. If the storage has not been initialized,
 /// initializes it with the result of `os::detect_features()`.
 ///
 /// On its first invocation, it detects the CPU features and caches them in the
-/// `CACHE` global variable as an `AtomicU64`.
+/// `CACHE` global variable as an array of `AtomicUsize`s.
 ///
 /// It uses the `Feature` variant to index into this variable as a bitset. If
 /// the bit is set, the feature is enabled, and otherwise it is disabled.
@@ -183,12 +539,18 @@ fn detect_and_initialize() -> Initializer {
 /// Features that would had been otherwise detected.
 #[inline]
 pub(crate) fn test(bit: u32) -> bool {
-    let (relative_bit, idx) = if bit < Cache::CAPACITY {
-        (bit, 0)
-    } else {
-        (bit - Cache::CAPACITY, 1)
-    };
-    CACHE[idx]
+    let (relative_bit, idx) = (bit % Cache::CAPACITY, (bit / Cache::CAPACITY) as usize);
+    #[cfg(feature = "std_detect_thread_local_cache")]
+    {
+        let cached = THREAD_CACHE[idx].get();
+        if cached != 0 {
+            return test_bit(cached as u128, relative_bit);
+        }
+    }
+    let enabled = CACHE[idx]
         .test(relative_bit)
-        .unwrap_or_else(|| detect_and_initialize().test(bit))
+        .unwrap_or_else(|| detect_and_initialize().test(bit));
+    #[cfg(feature = "std_detect_thread_local_cache")]
+    THREAD_CACHE[idx].set(CACHE[idx].0.load(Ordering::Relaxed));
+    enabled
 }
@@ -23,6 +23,27 @@ macro_rules! is_x86_feature_detected {
     };
 }
 
+/// Prevents compilation if `require_x86_features` is used somewhere else
+/// than `x86` and `x86_64` targets.
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+#[macro_export]
+#[unstable(feature = "stdsimd", issue = "27731")]
+macro_rules! require_x86_features {
+    ($($t: tt),+ $(,)?) => {
+        compile_error!(
+            r#"
+        require_x86_features can only be used on x86 and x86_64 targets.
+        You can prevent it from being used in other architectures by
+        guarding it behind a cfg(target_arch) as follows:
+
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+                require_x86_features!(...)?;
+            }
+        "#
+        )
+    };
+}
+
 /// Prevents compilation if `is_arm_feature_detected` is used somewhere else
 /// than `ARM` targets.
 #[cfg(not(target_arch = "arm"))]
@@ -14,7 +14,8 @@ macro_rules! features {
         macro_rules! $macro_name {
             $(
                 ($feature_lit) => {
-                    $crate::detect::__is_feature_detected::$feature()
+                    cfg!(target_feature = $feature_lit) ||
+                        $crate::detect::__is_feature_detected::$feature()
                 };
             )*
             $(
@@ -58,6 +59,8 @@ macro_rules! features {
                 $feature,
             )*
 
+            // New features are added at the end of the list, right before
+            // `_last`, so that the order of `features()` stays stable.
             // Do not add variants after last:
             _last
         }
@@ -69,13 +72,21 @@ macro_rules! features {
                     Feature::_last => unreachable!(),
                 }
             }
-            #[cfg(feature = "std_detect_env_override")]
             pub(crate) fn from_str(s: &str) -> Result<Feature, ()> {
                 match s {
                     $($feature_lit => Ok(Feature::$feature),)*
+                    $($bind_feature => Feature::from_str($feature_impl),)*
                     _ => Err(())
                 }
             }
+            /// Performs run-time feature detection, taking into account
+            /// features that are enabled at compile-time.
+            pub(crate) fn is_detected(self) -> bool {
+                match self {
+                    $(Feature::$feature => __is_feature_detected::$feature(),)*
+                    Feature::_last => unreachable!(),
+                }
+            }
         }
 
         /// Each function performs run-time feature detection for a single
@@ -101,3 +112,28 @@ macro_rules! features {
         }
     };
 }
+
+/// A macro to test at *runtime* whether all of the given CPU features are
+/// available on x86/x86-64 platforms.
+///
+/// This takes the same feature names as [`is_x86_feature_detected`], and
+/// returns `Ok(())` if all of them are detected, or a
+/// `std_detect::detect::MissingFeatures` error that lists the ones that are
+/// not, e.g., to report all of them at once at start-up:
+///
+/// ```ignore
+/// if let Err(missing) = require_x86_features!("avx2", "fma") {
+///     panic!("this build needs AVX2 and FMA; {}", missing);
+/// }
+/// ```
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[macro_export]
+#[unstable(feature = "stdsimd", issue = "27731")]
+#[allow_internal_unstable(stdsimd)]
+macro_rules! require_x86_features {
+    ($($t:tt),+ $(,)?) => {
+        $crate::detect::__require_features(&[
+            $(($t, $crate::is_x86_feature_detected!($t)),)+
+        ])
+    };
+}
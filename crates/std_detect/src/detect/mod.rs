@@ -4,10 +4,13 @@
 //! feature as a string-literal, and return a boolean indicating whether the
 //! feature is enabled at run-time or not.
 //!
-//! These macros do two things:
-//! * map the string-literal into an integer stored as a `Feature` enum,
-//! * call a `os::check_for(x: Feature)` function that returns `true` if the
-//! feature is enabled.
+//! These macros do three things:
+//! * return `true` without any run-time check if the feature is enabled at
+//! compile-time, e.g., with `-C target-feature`,
+//! * otherwise map the string-literal into an integer stored as a `Feature`
+//! enum,
+//! * and call a `os::check_for(x: Feature)` function that returns `true` if
+//! the feature is enabled.
 //!
 //! The `Feature` enums are also implemented in the `arch/{target_arch}.rs`
 //! modules.
@@ -70,6 +73,8 @@ cfg_if! {
                 pub(crate) fn from_str(_s: &str) -> Result<Feature, ()> { Err(()) }
                 #[doc(hidden)]
                 pub(crate) fn to_str(self) -> &'static str { "" }
+                #[doc(hidden)]
+                pub(crate) fn is_detected(self) -> bool { false }
             }
         }
     }
@@ -85,6 +90,12 @@ pub(crate) use self::arch::Feature;
 mod bit;
 mod cache;
 
+pub use self::cache::{
+    __detection_initialized, __diff_from_snapshot, __initialize_detection,
+    __install_feature_snapshot, __set_detection_observer, DetectionSource, FeatureDiff,
+    FeatureSnapshot, Initializer,
+};
+
 cfg_if! {
     if #[cfg(miri)] {
         // When running under miri all target-features that are not enabled at
@@ -102,20 +113,74 @@ cfg_if! {
         #[path = "os/linux/mod.rs"]
         mod os;
     } else if #[cfg(all(target_os = "freebsd", feature = "libc"))] {
-        #[cfg(target_arch = "aarch64")]
-        #[path = "os/aarch64.rs"]
-        mod aarch64;
         #[path = "os/freebsd/mod.rs"]
         mod os;
     } else if #[cfg(all(target_os = "windows", target_arch = "aarch64"))] {
         #[path = "os/windows/aarch64.rs"]
         mod os;
+    } else if #[cfg(all(target_os = "macos", target_arch = "aarch64", feature = "libc"))] {
+        #[path = "os/macos/aarch64.rs"]
+        mod os;
+    } else if #[cfg(all(target_os = "openbsd", target_arch = "aarch64", feature = "libc"))] {
+        #[path = "os/openbsd/aarch64.rs"]
+        mod os;
+    } else if #[cfg(all(target_os = "netbsd", target_arch = "aarch64", feature = "libc"))] {
+        #[path = "os/netbsd/aarch64.rs"]
+        mod os;
     } else {
         #[path = "os/other.rs"]
         mod os;
     }
 }
 
+// Decoding the system registers is available on all aarch64 targets for
+// `__evaluate_features_from`.
+#[cfg(target_arch = "aarch64")]
+#[cfg_attr(any(test, not(target_os = "freebsd")), allow(dead_code))]
+#[path = "os/aarch64.rs"]
+mod aarch64;
+
+#[cfg(all(
+    target_os = "macos",
+    any(target_arch = "aarch64", target_arch = "x86_64"),
+    feature = "libc"
+))]
+#[path = "os/macos.rs"]
+mod macos;
+#[cfg(all(
+    target_os = "macos",
+    any(target_arch = "aarch64", target_arch = "x86_64"),
+    feature = "libc"
+))]
+pub use self::macos::__is_translated;
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64", feature = "libc"))]
+#[path = "os/linux/cet.rs"]
+mod cet;
+#[cfg(all(target_os = "linux", target_arch = "x86_64", feature = "libc"))]
+pub use self::cet::__cet_shadow_stack_active;
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64", feature = "libc"))]
+#[path = "os/linux/cpuid_faulting.rs"]
+mod cpuid_faulting;
+
+#[cfg(all(
+    target_os = "linux",
+    target_arch = "x86_64",
+    feature = "std_detect_file_io"
+))]
+#[path = "os/linux/la57.rs"]
+mod la57;
+
+#[cfg(all(any(target_arch = "x86", target_arch = "x86_64"), not(miri)))]
+pub use self::os::{
+    __amd_topology, __avx10_max_vlen_bits, __avx10_version, __cpu_signature,
+    __has_avx_sse_transition_penalty, __has_thread_director, __has_tsc_adjust, __has_tsc_deadline,
+    __hwrng_trustworthy, __is_tdx_guest, __la57_active, __likely_emulated, __monitor_line_sizes,
+    __num_pmc, __sev_status, __thread_director_classes, __tpause_likely_usable, AmdTopology,
+    CpuSignature, SevStatus,
+};
+
 /// Performs run-time feature detection.
 #[inline]
 #[allow(dead_code)]
@@ -123,11 +188,194 @@ fn check_for(x: Feature) -> bool {
     cache::test(x as u32)
 }
 
+cfg_if! {
+    if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+        /// Features that imply other features, as `(feature, implied)` pairs.
+        ///
+        /// Only direct implications are listed; they are expanded transitively.
+        const IMPLIED_FEATURES: &[(Feature, Feature)] = &[
+            (Feature::sse2, Feature::sse),
+            (Feature::sse3, Feature::sse2),
+            (Feature::ssse3, Feature::sse3),
+            (Feature::sse4_1, Feature::ssse3),
+            (Feature::sse4_2, Feature::sse4_1),
+            (Feature::avx, Feature::sse4_2),
+            (Feature::avx2, Feature::avx),
+            (Feature::fma, Feature::avx),
+            (Feature::f16c, Feature::avx),
+            (Feature::gfni, Feature::sse2),
+            (Feature::vaes, Feature::avx),
+            (Feature::vaes, Feature::aes),
+            (Feature::vpclmulqdq, Feature::avx),
+            (Feature::vpclmulqdq, Feature::pclmulqdq),
+            (Feature::avx512f, Feature::avx2),
+            (Feature::avx512f, Feature::fma),
+            (Feature::avx512f, Feature::f16c),
+            (Feature::avx512cd, Feature::avx512f),
+            (Feature::avx512er, Feature::avx512f),
+            (Feature::avx512pf, Feature::avx512f),
+            (Feature::avx5124vnniw, Feature::avx512f),
+            (Feature::avx5124fmaps, Feature::avx512f),
+            (Feature::avx512bw, Feature::avx512f),
+            (Feature::avx512dq, Feature::avx512f),
+            (Feature::avx512vl, Feature::avx512f),
+            (Feature::avx512ifma, Feature::avx512f),
+            (Feature::avx512vbmi, Feature::avx512bw),
+            (Feature::avx512vpopcntdq, Feature::avx512f),
+            (Feature::avx512vbmi2, Feature::avx512bw),
+            (Feature::avx512vnni, Feature::avx512f),
+            (Feature::avx512bitalg, Feature::avx512bw),
+            (Feature::avx512bf16, Feature::avx512bw),
+            (Feature::avx512vp2intersect, Feature::avx512f),
+            (Feature::xsaveopt, Feature::xsave),
+            (Feature::xsaves, Feature::xsave),
+            (Feature::xsavec, Feature::xsave),
+            (Feature::avx10_1_256, Feature::avx2),
+            (Feature::avx10_1_256, Feature::fma),
+            (Feature::avx10_1_256, Feature::f16c),
+            (Feature::avx10_1_512, Feature::avx10_1_256),
+        ];
+    } else if #[cfg(target_arch = "aarch64")] {
+        /// Features that imply other features, as `(feature, implied)` pairs.
+        ///
+        /// Only direct implications are listed; they are expanded transitively.
+        const IMPLIED_FEATURES: &[(Feature, Feature)] = &[
+            (Feature::fp16, Feature::fp),
+            (Feature::asimd, Feature::fp),
+            (Feature::rdm, Feature::asimd),
+            (Feature::dotprod, Feature::asimd),
+            (Feature::sve, Feature::asimd),
+            (Feature::sve, Feature::fp16),
+            (Feature::sve2, Feature::sve),
+            (Feature::sve2p1, Feature::sve2),
+            (Feature::sme2, Feature::sme),
+            (Feature::sme2p1, Feature::sme2),
+            (Feature::fhm, Feature::asimd),
+            (Feature::fhm, Feature::fp16),
+            (Feature::sha512, Feature::asimd),
+            (Feature::sha3, Feature::sha512),
+            (Feature::sm4, Feature::asimd),
+            (Feature::fcma, Feature::asimd),
+            (Feature::jsconv, Feature::fp),
+            (Feature::dpb2, Feature::dpb),
+            (Feature::flagm2, Feature::flagm),
+            (Feature::crypto, Feature::asimd),
+            (Feature::crypto, Feature::pmull),
+            (Feature::crypto, Feature::aes),
+            // AES == 2 in ID_AA64ISAR0_EL1 means PMULL + AES.
+            (Feature::pmull, Feature::aes),
+        ];
+    } else {
+        const IMPLIED_FEATURES: &[(Feature, Feature)] = &[];
+    }
+}
+
+/// Enables every feature that is transitively implied by a feature enabled in
+/// `value`.
+///
+/// This is the only place where implications are expanded: it is applied to
+/// the result of `os::detect_features` and to installed snapshots before the
+/// cache is initialized, so that `check_for`, the
+/// `is_{arch}_feature_detected!` macros, and [`features`] all observe the same
+/// closure.
+fn expand_implied_features(value: &mut cache::Initializer, implied: &[(Feature, Feature)]) {
+    loop {
+        let mut changed = false;
+        for &(feature, implied) in implied {
+            if value.test(feature as u32) && !value.test(implied as u32) {
+                value.set(implied as u32);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
 /// Returns an `Iterator<Item=(&'static str, bool)>` where
 /// `Item.0` is the feature name, and `Item.1` is a `bool` which
 /// is `true` if the feature is supported by the host and `false` otherwise.
+///
+/// The features are yielded in the order in which they are declared for the
+/// target architecture, so the order is the same on every run, and features
+/// added in later versions are only appended at the end.
 #[unstable(feature = "stdsimd", issue = "27731")]
 pub fn features() -> impl Iterator<Item = (&'static str, bool)> {
+    feature_states(check_for)
+}
+
+/// The features that are required by a `require_{arch}_features!` macro, but
+/// are not detected.
+///
+/// Its `Display` implementation writes `missing target features: ` followed
+/// by the comma-separated feature names.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub struct MissingFeatures(cache::Initializer);
+
+#[unstable(feature = "stdsimd", issue = "27731")]
+impl MissingFeatures {
+    /// Returns the names of the missing features, in the same order as
+    /// [`features`].
+    ///
+    /// Features that have several names, like `"abm"` and `"lzcnt"`, are
+    /// reported with the name that [`features`] uses.
+    #[inline]
+    #[unstable(feature = "stdsimd", issue = "27731")]
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        feature_names(self.0)
+    }
+}
+
+#[unstable(feature = "stdsimd", issue = "27731")]
+impl core::fmt::Display for MissingFeatures {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("missing target features: ")?;
+        for (i, name) in self.names().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Implementation detail of the `require_{arch}_features!` macros: returns
+/// an error with the features of `detected` whose `bool` is `false`.
+///
+/// PLEASE: do not use this, it is an implementation detail subject to change.
+#[inline]
+#[doc(hidden)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __require_features(detected: &[(&str, bool)]) -> Result<(), MissingFeatures> {
+    let mut missing = cache::Initializer::default();
+    for &(name, enabled) in detected {
+        if !enabled {
+            if let Ok(f) = Feature::from_str(name) {
+                missing.set(f as u32);
+            }
+        }
+    }
+    if missing == cache::Initializer::default() {
+        Ok(())
+    } else {
+        Err(MissingFeatures(missing))
+    }
+}
+
+/// Returns the names of the features enabled in `value`, in the same order as
+/// [`features`].
+fn feature_names(value: cache::Initializer) -> impl Iterator<Item = &'static str> {
+    feature_states(move |f| value.test(f as u32))
+        .filter(|&(_, enabled)| enabled)
+        .map(|(name, _)| name)
+}
+
+/// Like [`features`], but whether a feature is enabled is decided by `has`.
+fn feature_states(has: impl Fn(Feature) -> bool) -> impl Iterator<Item = (&'static str, bool)> {
     cfg_if! {
         if #[cfg(any(
             target_arch = "x86",
@@ -139,14 +387,919 @@ pub fn features() -> impl Iterator<Item = (&'static str, bool)> {
             target_arch = "mips",
             target_arch = "mips64",
         ))] {
-            (0_u8..Feature::_last as u8).map(|discriminant: u8| {
+            (0_u8..Feature::_last as u8).map(move |discriminant: u8| {
                 let f: Feature = unsafe { core::mem::transmute(discriminant) };
                 let name: &'static str = f.to_str();
-                let enabled: bool = check_for(f);
+                let enabled: bool = has(f);
                 (name, enabled)
             })
+        } else {
+            drop(has);
+            None.into_iter()
+        }
+    }
+}
+
+/// Returns the highest microarchitecture level of the x86-64 psABI
+/// (`x86-64-v1` to `x86-64-v4`) that is fully satisfied by the features
+/// detected on the host, or `0` if not even `x86-64-v1` is.
+#[inline]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __x86_64_microarch_level() -> u8 {
+    x86_64_microarch_level(check_for)
+}
+
+/// The features required by each of the `x86-64-v{1,2,3,4}` levels in
+/// addition to those of the previous level.
+///
+/// Requirements that are not detected as separate features (e.g. `CMOV` and
+/// `CX8` for `x86-64-v1`) are omitted; `OSXSAVE` is covered by `xsave`.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const X86_64_MICROARCH_LEVELS: &[&[Feature]] = &[
+    &[Feature::fxsr, Feature::mmx, Feature::sse, Feature::sse2],
+    &[
+        Feature::cmpxchg16b,
+        Feature::lahfsahf,
+        Feature::popcnt,
+        Feature::sse3,
+        Feature::ssse3,
+        Feature::sse4_1,
+        Feature::sse4_2,
+    ],
+    &[
+        Feature::avx,
+        Feature::avx2,
+        Feature::bmi1,
+        Feature::bmi2,
+        Feature::f16c,
+        Feature::fma,
+        Feature::lzcnt,
+        Feature::movbe,
+        Feature::xsave,
+    ],
+    &[
+        Feature::avx512f,
+        Feature::avx512bw,
+        Feature::avx512cd,
+        Feature::avx512dq,
+        Feature::avx512vl,
+    ],
+];
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn x86_64_microarch_level(has: impl Fn(Feature) -> bool) -> u8 {
+    let mut level = 0;
+    for features in X86_64_MICROARCH_LEVELS {
+        if !features.iter().all(|&f| has(f)) {
+            break;
+        }
+        level += 1;
+    }
+    level
+}
+
+/// Returns `true` if all of `avx512f`, `avx512bw`, `avx512cd`, `avx512dq`,
+/// and `avx512vl` are detected on the host, i.e., the AVX-512 subset of
+/// `x86-64-v4`.
+#[inline]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __has_avx512_full() -> bool {
+    has_avx512_full(check_for)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn has_avx512_full(has: impl Fn(Feature) -> bool) -> bool {
+    X86_64_MICROARCH_LEVELS[3].iter().all(|&f| has(f))
+}
+
+/// Returns `true` if both `popcnt` and `lzcnt` are detected on the host.
+///
+/// AMD introduced both instructions as ABM (Advanced Bit Manipulation), but
+/// Intel reports them in different CPUID leaves and added `lzcnt` only with
+/// Haswell, so `is_x86_feature_detected!("abm")`, which is a synonym for
+/// `lzcnt`, does not imply `popcnt`.
+#[inline]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __has_abm() -> bool {
+    has_abm(check_for)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn has_abm(has: impl Fn(Feature) -> bool) -> bool {
+    has(Feature::popcnt) && has(Feature::lzcnt)
+}
+
+/// Returns `true` if the GFNI instructions can be used on 512-bit vectors,
+/// that is, if `gfni`, `avx512f`, and `avx512bw` are all detected.
+///
+/// `gfni` on its own only guarantees the SSE encodings, together with `avx`
+/// it also guarantees the VEX encodings.
+#[inline]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __has_gfni_avx512() -> bool {
+    has_gfni_avx512(check_for)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn has_gfni_avx512(has: impl Fn(Feature) -> bool) -> bool {
+    has(Feature::gfni) && has(Feature::avx512f) && has(Feature::avx512bw)
+}
+
+/// Returns `true` if Intel DL Boost, as introduced with Cascade Lake, is
+/// available, that is, if `avx512f`, `avx512bw`, and `avx512vnni` are all
+/// detected.
+#[inline]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __has_dlboost() -> bool {
+    has_dlboost(check_for)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn has_dlboost(has: impl Fn(Feature) -> bool) -> bool {
+    has(Feature::avx512f) && has(Feature::avx512bw) && has(Feature::avx512vnni)
+}
+
+/// Returns the highest Arm A-profile architecture version that is fully
+/// satisfied by the features detected on the host, as `10 * major + minor`
+/// (e.g. `82` for Armv8.2-A), or `0` if not even Armv8.0-A is.
+#[inline]
+#[cfg(target_arch = "aarch64")]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __aarch64_arch_level() -> u8 {
+    aarch64_arch_level(check_for)
+}
+
+/// The architecture versions and the features they require in addition to
+/// those of the previous version.
+///
+/// Only mandatory requirements that are detected as separate features are
+/// listed; optional extensions, like `fp16` in Armv8.2-A, are not.
+#[cfg(target_arch = "aarch64")]
+const AARCH64_ARCH_LEVELS: &[(u8, &[Feature])] = &[
+    (80, &[Feature::fp, Feature::asimd]),
+    (81, &[Feature::crc, Feature::lse, Feature::rdm]),
+    (82, &[Feature::dpb]),
+    (83, &[Feature::rcpc, Feature::jsconv, Feature::fcma]),
+    (84, &[Feature::dotprod, Feature::flagm, Feature::lse2]),
+    (90, &[Feature::sve2]),
+];
+
+/// The features that an architecture version requires if another feature is
+/// implemented, as `(version, if, then)`, e.g., Armv8.4-A requires FHM on
+/// cores with FP16.
+#[cfg(target_arch = "aarch64")]
+const AARCH64_ARCH_LEVELS_IF: &[(u8, Feature, Feature)] = &[(84, Feature::fp16, Feature::fhm)];
+
+#[cfg(target_arch = "aarch64")]
+fn aarch64_arch_level(has: impl Fn(Feature) -> bool) -> u8 {
+    let mut level = 0;
+    for &(version, features) in AARCH64_ARCH_LEVELS {
+        if !features.iter().all(|&f| has(f)) {
+            break;
+        }
+        if AARCH64_ARCH_LEVELS_IF
+            .iter()
+            .any(|&(v, f, g)| v == version && has(f) && !has(g))
+        {
+            break;
+        }
+        level = version;
+    }
+    level
+}
+
+/// Returns an `Iterator` over the names of the features that are guaranteed
+/// by the minimum target specification of the current architecture, e.g.,
+/// `sse2` on `x86_64` and `neon` on `aarch64`.
+///
+/// Features enabled with `-C target-feature` or `-C target-cpu` are not
+/// included. Architectures whose targets do not share a baseline, like `x86`
+/// (`i586` lacks SSE), yield nothing.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __baseline_features() -> impl Iterator<Item = &'static str> {
+    cfg_if! {
+        if #[cfg(target_arch = "x86_64")] {
+            // x86-64-v1
+            X86_64_MICROARCH_LEVELS[0].iter().map(|&f| f.to_str())
+        } else if #[cfg(target_arch = "aarch64")] {
+            // Armv8.0-A
+            AARCH64_ARCH_LEVELS[0].1.iter().map(|&f| f.to_str())
         } else {
             None.into_iter()
         }
     }
 }
+
+/// Returns the width in bits of the widest SIMD registers that are usable on
+/// the host, e.g., `512` on x86 with `avx512f`, or `0` if there are none.
+///
+/// On `aarch64` this is the SVE vector length if `sve` is detected. On
+/// `x86_64` and `aarch64`, whose baselines include 128-bit SIMD registers,
+/// this is at least `128`.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __max_vector_bits() -> u32 {
+    cfg_if! {
+        if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+            x86_max_vector_bits(check_for)
+        } else if #[cfg(target_arch = "aarch64")] {
+            aarch64_max_vector_bits(check_for, sve_vector_bits)
+        } else if #[cfg(target_arch = "arm")] {
+            if check_for(Feature::neon) { 128 } else { 0 }
+        } else if #[cfg(any(target_arch = "powerpc", target_arch = "powerpc64"))] {
+            if check_for(Feature::altivec) { 128 } else { 0 }
+        } else if #[cfg(any(target_arch = "mips", target_arch = "mips64"))] {
+            if check_for(Feature::msa) { 128 } else { 0 }
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn x86_max_vector_bits(has: impl Fn(Feature) -> bool) -> u32 {
+    if has(Feature::avx512f) || has(Feature::avx10_1_512) {
+        512
+    } else if has(Feature::avx) {
+        256
+    } else if has(Feature::sse) || cfg!(target_arch = "x86_64") {
+        128
+    } else if has(Feature::mmx) {
+        64
+    } else {
+        0
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn aarch64_max_vector_bits(has: impl Fn(Feature) -> bool, sve_bits: impl FnOnce() -> u32) -> u32 {
+    if has(Feature::sve) {
+        sve_bits()
+    } else {
+        128
+    }
+}
+
+/// Returns the SVE vector length in bits.
+///
+/// Must only be called if `sve` is detected.
+#[cfg(target_arch = "aarch64")]
+fn sve_vector_bits() -> u32 {
+    let bytes: u64;
+    unsafe {
+        // `rdvl x0, #1`, encoded so that assemblers without SVE support
+        // accept it.
+        asm!(
+            ".inst 0x04bf5020",
+            out("x0") bytes,
+            options(pure, nomem, preserves_flags, nostack)
+        );
+    }
+    bytes as u32 * 8
+}
+
+/// Writes the names of the features detected on the host into `buf`,
+/// separated by commas, and returns the number of bytes written.
+///
+/// This does not allocate. If `buf` is too small, the list is truncated after
+/// the last name that fits entirely, so that the written bytes are always a
+/// prefix of the full list that ends at a name boundary.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __write_detected_features(buf: &mut [u8]) -> usize {
+    write_feature_names(
+        buf,
+        features()
+            .filter(|&(_, enabled)| enabled)
+            .map(|(name, _)| name),
+    )
+}
+
+fn write_feature_names<'a>(buf: &mut [u8], names: impl Iterator<Item = &'a str>) -> usize {
+    let mut len = 0;
+    for name in names {
+        let start = if len == 0 { 0 } else { len + 1 };
+        let end = start + name.len();
+        if end > buf.len() {
+            break;
+        }
+        if len != 0 {
+            buf[len] = b',';
+        }
+        buf[start..end].copy_from_slice(name.as_bytes());
+        len = end;
+    }
+    len
+}
+
+/// The raw values that run-time feature detection decodes, see
+/// [`__evaluate_features_from`].
+#[cfg(any(
+    all(any(target_arch = "x86", target_arch = "x86_64"), not(miri)),
+    target_arch = "aarch64"
+))]
+#[derive(Copy, Clone, Debug)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub enum RawDetectionInput<'a> {
+    /// A dump of the `cpuid` instruction as `(leaf, sub_leaf, [eax, ebx, ecx,
+    /// edx])`, where leaves that are not listed read as zeros, and the value
+    /// of `XCR0`.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Cpuid {
+        /// The results of the `cpuid` instruction.
+        leaves: &'a [(u32, u32, [u32; 4])],
+        /// The result of `xgetbv(0)`.
+        xcr0: u64,
+    },
+    /// The values of `ID_AA64ISAR0_EL1`, `ID_AA64ISAR1_EL1`,
+    /// `ID_AA64MMFR0_EL1`, `ID_AA64MMFR2_EL1`, and `ID_AA64PFR0_EL1`, in that
+    /// order, of each core. Only the features supported by every core are
+    /// reported. The features reported in other registers, such as `rprfm`
+    /// and the SVE2 and SME features, are never reported.
+    #[cfg(target_arch = "aarch64")]
+    IdRegisters(&'a [[u64; 5]]),
+}
+
+/// Decodes the features from `input` with the same logic as run-time feature
+/// detection instead of querying the host, e.g., to evaluate which features
+/// would be detected on another machine from a dump of its CPUID leaves.
+///
+/// Implied features are expanded, but `RUST_STD_DETECT_UNSTABLE` is not
+/// applied. Use [`FeatureSnapshot::features`] to query the result.
+#[inline]
+#[cfg(any(
+    all(any(target_arch = "x86", target_arch = "x86_64"), not(miri)),
+    target_arch = "aarch64"
+))]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __evaluate_features_from(input: RawDetectionInput<'_>) -> FeatureSnapshot {
+    let mut value = match input {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        RawDetectionInput::Cpuid { leaves, xcr0 } => os::detect_features_from_dump(leaves, xcr0),
+        #[cfg(target_arch = "aarch64")]
+        RawDetectionInput::IdRegisters(cores) => aarch64::common_features(cores.iter().map(
+            |&[aa64isar0, aa64isar1, aa64mmfr0, aa64mmfr2, aa64pfr0]| aarch64::AA64Reg {
+                aa64isar0,
+                aa64isar1,
+                aa64mmfr0,
+                aa64mmfr2,
+                aa64pfr0,
+            },
+        )),
+    };
+    expand_implied_features(&mut value, IMPLIED_FEATURES);
+    FeatureSnapshot::from(value)
+}
+
+/// Returns a [`DetectionReport`] of the features detected on the host.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __detection_report() -> DetectionReport {
+    DetectionReport(())
+}
+
+/// Returns how the feature `name` has been detected, performing run-time
+/// feature detection if this has not happened yet, or `None` if `name` is
+/// not a feature of the target architecture.
+///
+/// This is meant for bug reports about run-time feature detection.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __detection_source(name: &str) -> Option<DetectionSource> {
+    let f = Feature::from_str(name).ok()?;
+    Some(if !f.is_detected() {
+        DetectionSource::NotDetected
+    } else if check_for(f) {
+        cache::detection_source()
+    } else {
+        DetectionSource::CompileTime
+    })
+}
+
+/// A human-readable report of run-time feature detection, e.g., for bug
+/// reports.
+///
+/// Its `Display` implementation writes the target architecture followed by
+/// one `feature: bool` line per feature, in the same order as [`features`].
+/// The report is streamed into the formatter, so it does not allocate.
+#[derive(Copy, Clone)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub struct DetectionReport(());
+
+#[unstable(feature = "stdsimd", issue = "27731")]
+impl core::fmt::Display for DetectionReport {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        cfg_if! {
+            if #[cfg(target_arch = "x86")] {
+                const TARGET_ARCH: &str = "x86";
+            } else if #[cfg(target_arch = "x86_64")] {
+                const TARGET_ARCH: &str = "x86_64";
+            } else if #[cfg(target_arch = "arm")] {
+                const TARGET_ARCH: &str = "arm";
+            } else if #[cfg(target_arch = "aarch64")] {
+                const TARGET_ARCH: &str = "aarch64";
+            } else if #[cfg(target_arch = "powerpc")] {
+                const TARGET_ARCH: &str = "powerpc";
+            } else if #[cfg(target_arch = "powerpc64")] {
+                const TARGET_ARCH: &str = "powerpc64";
+            } else if #[cfg(target_arch = "mips")] {
+                const TARGET_ARCH: &str = "mips";
+            } else if #[cfg(target_arch = "mips64")] {
+                const TARGET_ARCH: &str = "mips64";
+            } else {
+                const TARGET_ARCH: &str = "unknown";
+            }
+        }
+        writeln!(f, "target_arch: {}", TARGET_ARCH)?;
+        for (name, enabled) in features() {
+            writeln!(f, "{}: {}", name, enabled)?;
+        }
+        Ok(())
+    }
+}
+
+#[unstable(feature = "stdsimd", issue = "27731")]
+impl core::fmt::Debug for DetectionReport {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_arch = "aarch64")]
+    use std::vec::Vec;
+
+    /// Expands the implications of `feature` alone.
+    #[allow(dead_code)]
+    fn implied_by(feature: Feature) -> cache::Initializer {
+        let mut value = cache::Initializer::default();
+        value.set(feature as u32);
+        expand_implied_features(&mut value, IMPLIED_FEATURES);
+        value
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn x86_implication_chains() {
+        let value = implied_by(Feature::avx512vbmi);
+        for &f in &[
+            Feature::avx512vbmi,
+            Feature::avx512bw,
+            Feature::avx512f,
+            Feature::avx2,
+            Feature::fma,
+            Feature::f16c,
+            Feature::avx,
+            Feature::sse4_2,
+            Feature::sse4_1,
+            Feature::ssse3,
+            Feature::sse3,
+            Feature::sse2,
+            Feature::sse,
+        ] {
+            assert!(value.test(f as u32), "{} not implied", f.to_str());
+        }
+        for &f in &[Feature::avx512cd, Feature::avx512vl, Feature::xsave] {
+            assert!(!value.test(f as u32), "{} implied", f.to_str());
+        }
+
+        let value = implied_by(Feature::xsavec);
+        assert!(value.test(Feature::xsave as u32));
+        assert!(!value.test(Feature::avx as u32));
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn x86_dl_boost_implication_chains() {
+        let value = implied_by(Feature::avx512vnni);
+        for &f in &[Feature::avx512f, Feature::avx2, Feature::avx] {
+            assert!(value.test(f as u32), "{} not implied", f.to_str());
+        }
+        let value = implied_by(Feature::avx512bf16);
+        for &f in &[Feature::avx512bw, Feature::avx512f] {
+            assert!(value.test(f as u32), "{} not implied", f.to_str());
+        }
+
+        if check_for(Feature::avx512vnni) {
+            assert!(check_for(Feature::avx512f));
+        }
+        if check_for(Feature::avx512bf16) {
+            assert!(check_for(Feature::avx512bw));
+        }
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn x86_64_microarch_levels() {
+        let level = |features: &[&[Feature]]| {
+            x86_64_microarch_level(|f| {
+                features
+                    .iter()
+                    .flat_map(|l| l.iter())
+                    .any(|&g| g as u8 == f as u8)
+            })
+        };
+        let levels = X86_64_MICROARCH_LEVELS;
+        assert_eq!(level(&[]), 0);
+        assert_eq!(level(&levels[..1]), 1);
+        assert_eq!(level(&levels[..2]), 2);
+        assert_eq!(level(&levels[..3]), 3);
+        assert_eq!(level(levels), 4);
+
+        // A level is only satisfied if all of its features are detected:
+        assert_eq!(level(&[levels[0], &levels[1][1..]]), 1);
+        assert_eq!(
+            level(&[levels[0], levels[1], &levels[2][..8], levels[3]]),
+            2
+        );
+        assert_eq!(level(&[&levels[0][1..], levels[1], levels[2]]), 0);
+        // ... and so are all of the levels below it:
+        assert_eq!(level(&[levels[0], levels[1], levels[3]]), 2);
+        assert_eq!(level(&levels[1..]), 0);
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn x86_avx512_full() {
+        let full = |features: &[Feature]| {
+            has_avx512_full(|f| features.iter().any(|&g| g as u8 == f as u8))
+        };
+        let avx512 = [
+            Feature::avx512f,
+            Feature::avx512bw,
+            Feature::avx512cd,
+            Feature::avx512dq,
+            Feature::avx512vl,
+        ];
+        assert!(full(&avx512));
+        assert!(!full(&[]));
+        for i in 0..avx512.len() {
+            let mut features = avx512;
+            // Replace one of the required features with an unrelated one:
+            features[i] = Feature::avx512vnni;
+            assert!(!full(&features), "{} not required", avx512[i].to_str());
+        }
+        // Knights Landing has avx512f and avx512cd, but not the others:
+        assert!(!full(&[
+            Feature::avx512f,
+            Feature::avx512cd,
+            Feature::avx512er,
+            Feature::avx512pf
+        ]));
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn require_features() {
+        assert_eq!(__require_features(&[("avx2", true), ("fma", true)]), Ok(()));
+        let missing = __require_features(&[("avx2", true), ("fma", false)]).unwrap_err();
+        assert_eq!(missing.names().collect::<std::vec::Vec<_>>(), ["fma"]);
+        assert_eq!(format!("{}", missing), "missing target features: fma");
+        // In the order of `features`, and with the names that it uses:
+        let missing =
+            __require_features(&[("fma", false), ("sse", true), ("abm", false)]).unwrap_err();
+        assert_eq!(
+            missing.names().collect::<std::vec::Vec<_>>(),
+            ["fma", "lzcnt"]
+        );
+        assert_eq!(
+            format!("{}", missing),
+            "missing target features: fma, lzcnt"
+        );
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn x86_abm() {
+        let abm = |features: &[Feature]| has_abm(|f| features.iter().any(|&g| g as u8 == f as u8));
+        assert!(abm(&[Feature::popcnt, Feature::lzcnt]));
+        // Intel Nehalem to Ivy Bridge have popcnt, but not lzcnt:
+        assert!(!abm(&[Feature::popcnt, Feature::sse4_2]));
+        assert!(!abm(&[Feature::lzcnt]));
+        assert!(!abm(&[]));
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn x86_gfni_avx512() {
+        let gfni_avx512 = |features: &[Feature]| {
+            has_gfni_avx512(|f| features.iter().any(|&g| g as u8 == f as u8))
+        };
+        // Ice Lake and later:
+        assert!(gfni_avx512(&[
+            Feature::gfni,
+            Feature::avx2,
+            Feature::avx512f,
+            Feature::avx512bw
+        ]));
+        // Tremont, with GFNI but without AVX:
+        assert!(!gfni_avx512(&[Feature::gfni, Feature::sse2]));
+        // Alder Lake, with GFNI but only AVX2:
+        assert!(!gfni_avx512(&[Feature::gfni, Feature::avx, Feature::avx2]));
+        assert!(!gfni_avx512(&[Feature::gfni, Feature::avx512f]));
+        // Skylake-SP, with AVX-512 but without GFNI:
+        assert!(!gfni_avx512(&[Feature::avx512f, Feature::avx512bw]));
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn x86_dlboost() {
+        let dlboost =
+            |features: &[Feature]| has_dlboost(|f| features.iter().any(|&g| g as u8 == f as u8));
+        // Cascade Lake:
+        assert!(dlboost(&[
+            Feature::avx512f,
+            Feature::avx512bw,
+            Feature::avx512vnni
+        ]));
+        // Skylake-SP, without VNNI:
+        assert!(!dlboost(&[Feature::avx512f, Feature::avx512bw]));
+        assert!(!dlboost(&[Feature::avx512f, Feature::avx512vnni]));
+        assert!(!dlboost(&[Feature::avx512bw, Feature::avx512vnni]));
+        assert!(!dlboost(&[]));
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn x86_vector_bits() {
+        let bits = |features: &[Feature]| {
+            x86_max_vector_bits(|f| features.iter().any(|&g| g as u8 == f as u8))
+        };
+        assert_eq!(bits(&[Feature::sse, Feature::sse2]), 128);
+        assert_eq!(bits(&[Feature::sse, Feature::sse2, Feature::avx]), 256);
+        assert_eq!(bits(&[Feature::avx, Feature::avx2]), 256);
+        assert_eq!(bits(&[Feature::avx, Feature::avx512f]), 512);
+        // AVX10 without the legacy AVX-512 enumeration:
+        assert_eq!(bits(&[Feature::avx, Feature::avx10_1_256]), 256);
+        assert_eq!(bits(&[Feature::avx, Feature::avx10_1_512]), 512);
+        // 128-bit SSE registers are part of the x86_64 baseline:
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(bits(&[]), 128);
+        #[cfg(target_arch = "x86")]
+        {
+            assert_eq!(bits(&[Feature::mmx]), 64);
+            assert_eq!(bits(&[]), 0);
+        }
+
+        #[cfg(not(miri))]
+        {
+            let bits = __max_vector_bits();
+            assert!(bits >= 128);
+            assert_eq!(bits >= 256, check_for(Feature::avx));
+            assert_eq!(
+                bits == 512,
+                check_for(Feature::avx512f) || check_for(Feature::avx10_1_512)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn aarch64_vector_bits() {
+        let bits = |features: &[Feature], sve_bits: u32| {
+            aarch64_max_vector_bits(
+                |f| features.iter().any(|&g| g as u8 == f as u8),
+                || sve_bits,
+            )
+        };
+        assert_eq!(bits(&[], 0), 128);
+        assert_eq!(bits(&[Feature::asimd], 0), 128);
+        // The SVE vector length can be anything between 128 and 2048 bits:
+        assert_eq!(bits(&[Feature::asimd, Feature::sve], 128), 128);
+        assert_eq!(bits(&[Feature::asimd, Feature::sve], 256), 256);
+        assert_eq!(bits(&[Feature::asimd, Feature::sve], 2048), 2048);
+
+        assert!(__max_vector_bits() >= 128);
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn aarch64_arch_levels() {
+        let level = |features: &[Feature]| {
+            aarch64_arch_level(|f| features.iter().any(|&g| g as u8 == f as u8))
+        };
+        let without = |features: &[Feature], f: Feature| -> Vec<Feature> {
+            features
+                .iter()
+                .cloned()
+                .filter(|&g| g as u8 != f as u8)
+                .collect()
+        };
+        assert_eq!(level(&[]), 0);
+        assert_eq!(level(&[Feature::asimd]), 0);
+
+        // Cortex-A53, Armv8.0-A:
+        let cortex_a53 = [
+            Feature::fp,
+            Feature::asimd,
+            Feature::aes,
+            Feature::pmull,
+            Feature::crypto,
+            Feature::crc,
+        ];
+        assert_eq!(level(&cortex_a53), 80);
+
+        // Neoverse N1 and Cortex-A76, Armv8.2-A with the optional RCPC and
+        // DotProd extensions of Armv8.3-A and Armv8.4-A:
+        let neoverse_n1 = [
+            Feature::fp,
+            Feature::asimd,
+            Feature::aes,
+            Feature::pmull,
+            Feature::crypto,
+            Feature::crc,
+            Feature::lse,
+            Feature::rdm,
+            Feature::fp16,
+            Feature::dpb,
+            Feature::rcpc,
+            Feature::dotprod,
+        ];
+        assert_eq!(level(&neoverse_n1), 82);
+        // Armv8.2-A requires DC CVAP:
+        assert_eq!(level(&without(&neoverse_n1, Feature::dpb)), 81);
+
+        // Apple M1, Armv8.4-A and later:
+        let apple_m1 = [
+            Feature::fp,
+            Feature::asimd,
+            Feature::aes,
+            Feature::pmull,
+            Feature::crypto,
+            Feature::crc,
+            Feature::lse,
+            Feature::rdm,
+            Feature::fp16,
+            Feature::dpb,
+            Feature::rcpc,
+            Feature::dotprod,
+            Feature::jsconv,
+            Feature::fcma,
+            Feature::flagm,
+            Feature::lse2,
+            Feature::fhm,
+            Feature::sha3,
+            Feature::sha512,
+        ];
+        assert!(level(&apple_m1) >= 84);
+        // With FP16, Armv8.4-A also requires FHM:
+        let no_fhm = without(&apple_m1, Feature::fhm);
+        assert_eq!(level(&no_fhm), 83);
+        // ... but not without it:
+        assert_eq!(level(&without(&no_fhm, Feature::fp16)), 84);
+
+        // Neoverse N2, Armv9.0-A:
+        let mut neoverse_n2 = apple_m1.to_vec();
+        neoverse_n2.extend_from_slice(&[Feature::sve, Feature::sve2]);
+        assert_eq!(level(&neoverse_n2), 90);
+        // SVE2 alone is not enough:
+        assert_eq!(level(&[&neoverse_n1[..], &[Feature::sve2]].concat()), 82);
+    }
+
+    #[test]
+    fn baseline_features() {
+        // Detection is always available through CPUID on x86_64, but not
+        // necessarily on other architectures.
+        #[cfg(all(target_arch = "x86_64", not(miri)))]
+        for name in __baseline_features() {
+            assert!(
+                features().any(|(f, enabled)| f == name && enabled),
+                "{} not detected",
+                name
+            );
+        }
+        #[cfg(target_arch = "x86_64")]
+        assert!(__baseline_features().any(|f| f == "sse2"));
+        #[cfg(target_arch = "aarch64")]
+        {
+            assert!(__baseline_features().any(|f| f == "neon"));
+            assert!(__baseline_features().any(|f| f == "fp"));
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn aarch64_implication_chains() {
+        let value = implied_by(Feature::sve);
+        for &f in &[Feature::sve, Feature::asimd, Feature::fp16, Feature::fp] {
+            assert!(value.test(f as u32), "{} not implied", f.to_str());
+        }
+        assert!(!value.test(Feature::dotprod as u32));
+
+        let value = implied_by(Feature::crypto);
+        for &f in &[Feature::pmull, Feature::asimd, Feature::fp] {
+            assert!(value.test(f as u32), "{} not implied", f.to_str());
+        }
+
+        let value = implied_by(Feature::sve2p1);
+        for &f in &[Feature::sve2, Feature::sve, Feature::asimd, Feature::fp] {
+            assert!(value.test(f as u32), "{} not implied", f.to_str());
+        }
+        assert!(!value.test(Feature::sme as u32));
+        assert!(!implied_by(Feature::sve2).test(Feature::sve2p1 as u32));
+
+        let value = implied_by(Feature::sme2p1);
+        for &f in &[Feature::sme2, Feature::sme] {
+            assert!(value.test(f as u32), "{} not implied", f.to_str());
+        }
+        assert!(!value.test(Feature::sve as u32));
+        assert!(!implied_by(Feature::sme2).test(Feature::sme2p1 as u32));
+    }
+
+    #[test]
+    fn write_feature_names_truncates_at_name_boundary() {
+        let names = ["sse", "sse2", "avx512f"];
+        let write = |len: usize| {
+            let mut buf = [0xff_u8; 32];
+            let written = write_feature_names(&mut buf[..len], names.iter().copied());
+            // Nothing is written past the returned length:
+            assert!(buf[written..].iter().all(|&b| b == 0xff));
+            std::string::String::from_utf8(buf[..written].to_vec()).unwrap()
+        };
+        assert_eq!(write(32), "sse,sse2,avx512f");
+        assert_eq!(write(16), "sse,sse2,avx512f");
+        assert_eq!(write(15), "sse,sse2");
+        assert_eq!(write(8), "sse,sse2");
+        assert_eq!(write(7), "sse");
+        assert_eq!(write(3), "sse");
+        assert_eq!(write(2), "");
+        assert_eq!(write(0), "");
+
+        let mut buf = [0_u8; 4];
+        assert_eq!(write_feature_names(&mut buf, None.into_iter()), 0);
+    }
+
+    #[test]
+    fn write_detected_features() {
+        let detected: std::vec::Vec<&str> = features()
+            .filter(|&(_, enabled)| enabled)
+            .map(|(name, _)| name)
+            .collect();
+        let mut buf = [0_u8; 4096];
+        let written = __write_detected_features(&mut buf);
+        assert_eq!(&buf[..written], detected.join(",").as_bytes());
+    }
+
+    #[test]
+    fn implied_features_are_expanded() {
+        for &(feature, implied) in IMPLIED_FEATURES {
+            assert!(
+                feature as u8 != implied as u8,
+                "{} implies itself",
+                feature.to_str()
+            );
+            // Each feature implies its direct prerequisites...
+            let value = implied_by(feature);
+            assert!(
+                value.test(implied as u32),
+                "{} does not imply {}",
+                feature.to_str(),
+                implied.to_str()
+            );
+            // ... and the prerequisites of those, transitively:
+            for &(f, g) in IMPLIED_FEATURES {
+                if value.test(f as u32) {
+                    assert!(
+                        value.test(g as u32),
+                        "{} implies {} but not {}",
+                        feature.to_str(),
+                        f.to_str(),
+                        g.to_str()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn detected_features_are_closed() {
+        for &(feature, implied) in IMPLIED_FEATURES {
+            if check_for(feature) {
+                assert!(
+                    check_for(implied),
+                    "{} detected without {}",
+                    feature.to_str(),
+                    implied.to_str()
+                );
+            }
+            if feature.is_detected() {
+                assert!(
+                    implied.is_detected(),
+                    "{} detected without {}",
+                    feature.to_str(),
+                    implied.to_str()
+                );
+            }
+        }
+    }
+}
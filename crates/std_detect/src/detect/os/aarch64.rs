@@ -18,10 +18,79 @@
 
 use crate::detect::{cache, Feature};
 
+/// The values of the system registers that feature detection reads.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct AA64Reg {
+    /// ID_AA64ISAR0_EL1 - Instruction Set Attribute Register 0
+    pub(crate) aa64isar0: u64,
+    /// ID_AA64ISAR1_EL1 - Instruction Set Attribute Register 1
+    pub(crate) aa64isar1: u64,
+    /// ID_AA64MMFR0_EL1 - Memory Model Feature Register 0
+    pub(crate) aa64mmfr0: u64,
+    /// ID_AA64MMFR2_EL1 - Memory Model Feature Register 2
+    pub(crate) aa64mmfr2: u64,
+    /// ID_AA64PFR0_EL1 - Processor Feature Register 0
+    pub(crate) aa64pfr0: u64,
+}
+
 /// Try to read the features from the system registers.
 ///
 /// This will cause SIGILL if the current OS is not trapping the mrs instruction.
 pub(crate) fn detect_features() -> cache::Initializer {
+    let aa64isar0: u64;
+    let aa64isar1: u64;
+    let aa64mmfr0: u64;
+    let aa64mmfr2: u64;
+    let aa64pfr0: u64;
+    unsafe {
+        asm!(
+            "mrs {}, ID_AA64ISAR0_EL1",
+            out(reg) aa64isar0,
+            options(pure, nomem, preserves_flags, nostack)
+        );
+        asm!(
+            "mrs {}, ID_AA64ISAR1_EL1",
+            out(reg) aa64isar1,
+            options(pure, nomem, preserves_flags, nostack)
+        );
+        asm!(
+            "mrs {}, ID_AA64MMFR0_EL1",
+            out(reg) aa64mmfr0,
+            options(pure, nomem, preserves_flags, nostack)
+        );
+        // ID_AA64MMFR2_EL1, spelled out so that assemblers that predate
+        // Armv8.2-A accept it.
+        asm!(
+            "mrs {}, S3_0_C0_C7_2",
+            out(reg) aa64mmfr2,
+            options(pure, nomem, preserves_flags, nostack)
+        );
+        asm!(
+            "mrs {}, ID_AA64PFR0_EL1",
+            out(reg) aa64pfr0,
+            options(pure, nomem, preserves_flags, nostack)
+        );
+    }
+
+    cache::set_detection_source(cache::DetectionSource::Mrs);
+    parse_system_registers(AA64Reg {
+        aa64isar0,
+        aa64isar1,
+        aa64mmfr0,
+        aa64mmfr2,
+        aa64pfr0,
+    })
+}
+
+/// Decodes the features from the values of the system registers.
+pub(crate) fn parse_system_registers(regs: AA64Reg) -> cache::Initializer {
+    let AA64Reg {
+        aa64isar0,
+        aa64isar1,
+        aa64mmfr0,
+        aa64mmfr2,
+        aa64pfr0,
+    } = regs;
     let mut value = cache::Initializer::default();
 
     {
@@ -32,40 +101,27 @@ pub(crate) fn detect_features() -> cache::Initializer {
         };
 
         // ID_AA64ISAR0_EL1 - Instruction Set Attribute Register 0
-        let aa64isar0: u64;
-        unsafe {
-            asm!(
-                "mrs {}, ID_AA64ISAR0_EL1",
-                out(reg) aa64isar0,
-                options(pure, nomem, preserves_flags, nostack)
-            );
-        }
-
         let aes = bits_shift(aa64isar0, 7, 4) >= 1;
         let pmull = bits_shift(aa64isar0, 7, 4) >= 2;
         let sha1 = bits_shift(aa64isar0, 11, 8) >= 1;
         let sha2 = bits_shift(aa64isar0, 15, 12) >= 1;
+        // AES == 2 means that PMULL is supported in addition to AES:
+        enable_feature(Feature::aes, aes);
         enable_feature(Feature::pmull, pmull);
         // Crypto is specified as AES + PMULL + SHA1 + SHA2 per LLVM/hosts.cpp
         enable_feature(Feature::crypto, aes && pmull && sha1 && sha2);
         enable_feature(Feature::tme, bits_shift(aa64isar0, 27, 24) == 1);
         enable_feature(Feature::lse, bits_shift(aa64isar0, 23, 20) >= 1);
         enable_feature(Feature::crc, bits_shift(aa64isar0, 19, 16) >= 1);
+        enable_feature(Feature::flagm, bits_shift(aa64isar0, 55, 52) >= 1);
+        enable_feature(Feature::flagm2, bits_shift(aa64isar0, 55, 52) >= 2);
 
         // ID_AA64PFR0_EL1 - Processor Feature Register 0
-        let aa64pfr0: u64;
-        unsafe {
-            asm!(
-                "mrs {}, ID_AA64PFR0_EL1",
-                out(reg) aa64pfr0,
-                options(pure, nomem, preserves_flags, nostack)
-            );
-        }
-
+        // 0xF means not implemented, which does not include half-floats:
         let fp = bits_shift(aa64pfr0, 19, 16) < 0xF;
-        let fphp = bits_shift(aa64pfr0, 19, 16) >= 1;
+        let fphp = fp && bits_shift(aa64pfr0, 19, 16) >= 1;
         let asimd = bits_shift(aa64pfr0, 23, 20) < 0xF;
-        let asimdhp = bits_shift(aa64pfr0, 23, 20) >= 1;
+        let asimdhp = asimd && bits_shift(aa64pfr0, 23, 20) >= 1;
         enable_feature(Feature::fp, fp);
         enable_feature(Feature::fp16, fphp);
         // SIMD support requires float support - if half-floats are
@@ -78,24 +134,438 @@ pub(crate) fn detect_features() -> cache::Initializer {
             asimd && bits_shift(aa64isar0, 47, 44) >= 1,
         );
         enable_feature(Feature::sve, asimd && bits_shift(aa64pfr0, 35, 32) >= 1);
+        // SHA512 and SHA3 are only reported together with the rest of the
+        // SHA extensions, like Linux does:
+        let sha512 = asimd && sha1 && bits_shift(aa64isar0, 15, 12) >= 2;
+        enable_feature(Feature::sha512, sha512);
+        enable_feature(Feature::sha3, sha512 && bits_shift(aa64isar0, 35, 32) >= 1);
+        // SM4 is only reported together with SM3, like Fuchsia does:
+        enable_feature(
+            Feature::sm4,
+            asimd && bits_shift(aa64isar0, 39, 36) >= 1 && bits_shift(aa64isar0, 43, 40) >= 1,
+        );
+        // FHM also requires half-float SIMD support:
+        enable_feature(
+            Feature::fhm,
+            asimd && asimdhp && bits_shift(aa64isar0, 51, 48) >= 1,
+        );
 
         // ID_AA64ISAR1_EL1 - Instruction Set Attribute Register 1
-        let aa64isar1: u64;
-        unsafe {
-            asm!(
-                "mrs {}, ID_AA64ISAR1_EL1",
-                out(reg) aa64isar1,
-                options(pure, nomem, preserves_flags, nostack)
-            );
-        }
-
         enable_feature(Feature::rcpc, bits_shift(aa64isar1, 23, 20) >= 1);
+        enable_feature(Feature::dpb, bits_shift(aa64isar1, 3, 0) >= 1);
+        enable_feature(Feature::dpb2, bits_shift(aa64isar1, 3, 0) >= 2);
+        enable_feature(Feature::jsconv, fp && bits_shift(aa64isar1, 15, 12) >= 1);
+        enable_feature(Feature::fcma, asimd && bits_shift(aa64isar1, 19, 16) >= 1);
+        enable_feature(Feature::predres, bits_shift(aa64isar1, 43, 40) >= 1);
+
+        // ID_AA64MMFR0_EL1 - Memory Model Feature Register 0
+        enable_feature(Feature::ecv, bits_shift(aa64mmfr0, 63, 60) >= 1);
+
+        // ID_AA64MMFR2_EL1 - Memory Model Feature Register 2
+        enable_feature(Feature::lse2, bits_shift(aa64mmfr2, 35, 32) >= 1);
     }
 
     value
 }
 
+/// Decodes the features that are supported by every core from the values of
+/// the system registers of each core, for OSes that report them per core.
+///
+/// Returns no features if `cores` is empty.
+pub(crate) fn common_features(cores: impl IntoIterator<Item = AA64Reg>) -> cache::Initializer {
+    let mut common: Option<cache::Initializer> = None;
+    for regs in cores {
+        let value = parse_system_registers(regs);
+        common = Some(match common {
+            None => value,
+            Some(mut common) => {
+                for bit in 0..Feature::_last as u32 {
+                    if !value.test(bit) {
+                        common.unset(bit);
+                    }
+                }
+                common
+            }
+        });
+    }
+    common.unwrap_or_default()
+}
+
+/// Disables `sve` in `value` unless every core reports the same SVE
+/// features.
+///
+/// `aa64zfr0s` yields the `ID_AA64ZFR0_EL1` of each core, or `None` if the
+/// core does not implement SVE. On asymmetric SoCs code that migrates between
+/// cores with different SVE features would otherwise fault. The vector
+/// lengths are not compared, so cores with the same features but different
+/// vector lengths are not detected.
+#[cfg_attr(not(target_os = "netbsd"), allow(dead_code))]
+pub(crate) fn disable_non_uniform_sve(
+    value: &mut cache::Initializer,
+    aa64zfr0s: impl IntoIterator<Item = Option<u64>>,
+) {
+    let mut aa64zfr0s = aa64zfr0s.into_iter();
+    let uniform = match aa64zfr0s.next() {
+        Some(Some(first)) => aa64zfr0s.all(|aa64zfr0| aa64zfr0 == Some(first)),
+        _ => false,
+    };
+    if !uniform {
+        value.unset(Feature::sve as u32);
+    }
+}
+
 #[inline]
 fn bits_shift(x: u64, high: usize, low: usize) -> u64 {
     (x >> low) & ((1 << (high - low + 1)) - 1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The system registers of a Neoverse N1, which supports Armv8.2-A.
+    const NEOVERSE_N1: AA64Reg = AA64Reg {
+        aa64isar0: 0x0000_1000_1021_1120,
+        aa64isar1: 0x0000_0000_0010_0001,
+        aa64mmfr0: 0x0000_0000_0010_1125,
+        aa64mmfr2: 0x0000_0000_0000_1011,
+        aa64pfr0: 0x1100_0000_1011_1111,
+    };
+
+    #[test]
+    fn neoverse_n1() {
+        let value = parse_system_registers(NEOVERSE_N1);
+        for &f in &[
+            Feature::fp,
+            Feature::fp16,
+            Feature::asimd,
+            Feature::aes,
+            Feature::pmull,
+            Feature::crc,
+            Feature::crypto,
+            Feature::lse,
+            Feature::rdm,
+            Feature::rcpc,
+            Feature::dotprod,
+            Feature::dpb,
+        ] {
+            assert!(value.test(f as u32), "{} not detected", f.to_str());
+        }
+        for &f in &[
+            Feature::sve,
+            Feature::tme,
+            Feature::lse2,
+            Feature::fhm,
+            Feature::sha512,
+            Feature::sha3,
+            Feature::sm4,
+            Feature::fcma,
+            Feature::jsconv,
+            Feature::dpb2,
+            Feature::flagm,
+            Feature::flagm2,
+        ] {
+            assert!(!value.test(f as u32), "{} detected", f.to_str());
+        }
+    }
+
+    #[test]
+    fn aes() {
+        // ID_AA64ISAR0_EL1.AES[7:4]:
+        let regs = |aes: u64| AA64Reg {
+            aa64isar0: NEOVERSE_N1.aa64isar0 & !(0xf << 4) | aes << 4,
+            ..NEOVERSE_N1
+        };
+        let detect = |regs| {
+            let value = parse_system_registers(regs);
+            (
+                value.test(Feature::aes as u32),
+                value.test(Feature::pmull as u32),
+            )
+        };
+        assert_eq!(detect(regs(0)), (false, false));
+        assert_eq!(detect(regs(1)), (true, false));
+        assert_eq!(detect(regs(2)), (true, true));
+    }
+
+    #[test]
+    fn crc() {
+        // ID_AA64ISAR0_EL1.CRC32[19:16]:
+        let regs = |crc32: u64| AA64Reg {
+            aa64isar0: NEOVERSE_N1.aa64isar0 & !(0xf << 16) | crc32 << 16,
+            ..NEOVERSE_N1
+        };
+        assert!(!parse_system_registers(regs(0)).test(Feature::crc as u32));
+        assert!(parse_system_registers(regs(1)).test(Feature::crc as u32));
+        // Only the CRC32 field matters:
+        let regs = AA64Reg {
+            aa64isar0: !(0xf << 16),
+            ..AA64Reg::default()
+        };
+        assert!(!parse_system_registers(regs).test(Feature::crc as u32));
+    }
+
+    #[test]
+    fn rdm() {
+        // ID_AA64ISAR0_EL1.RDM[31:28]:
+        let regs = |rdm: u64| AA64Reg {
+            aa64isar0: NEOVERSE_N1.aa64isar0 & !(0xf << 28) | rdm << 28,
+            ..NEOVERSE_N1
+        };
+        assert!(!parse_system_registers(regs(0)).test(Feature::rdm as u32));
+        assert!(parse_system_registers(regs(1)).test(Feature::rdm as u32));
+        // RDM requires SIMD support, ID_AA64PFR0_EL1.AdvSIMD[23:20]:
+        let regs = AA64Reg {
+            aa64pfr0: NEOVERSE_N1.aa64pfr0 | 0xf << 20,
+            ..regs(1)
+        };
+        assert!(!parse_system_registers(regs).test(Feature::rdm as u32));
+    }
+
+    #[test]
+    fn dotprod() {
+        // ID_AA64ISAR0_EL1.DP[47:44]:
+        let regs = |dp: u64| AA64Reg {
+            aa64isar0: NEOVERSE_N1.aa64isar0 & !(0xf << 44) | dp << 44,
+            ..NEOVERSE_N1
+        };
+        assert!(!parse_system_registers(regs(0)).test(Feature::dotprod as u32));
+        assert!(parse_system_registers(regs(1)).test(Feature::dotprod as u32));
+        // Dot product instructions require SIMD support, ID_AA64PFR0_EL1.AdvSIMD[23:20]:
+        let regs = AA64Reg {
+            aa64pfr0: NEOVERSE_N1.aa64pfr0 | 0xf << 20,
+            ..regs(1)
+        };
+        assert!(!parse_system_registers(regs).test(Feature::dotprod as u32));
+    }
+
+    #[test]
+    fn fhm() {
+        // ID_AA64ISAR0_EL1.FHM[51:48]:
+        let regs = |fhm: u64| AA64Reg {
+            aa64isar0: NEOVERSE_N1.aa64isar0 & !(0xf << 48) | fhm << 48,
+            ..NEOVERSE_N1
+        };
+        assert!(!parse_system_registers(regs(0)).test(Feature::fhm as u32));
+        assert!(parse_system_registers(regs(1)).test(Feature::fhm as u32));
+        // FHM requires half-float SIMD support, ID_AA64PFR0_EL1.AdvSIMD[23:20]:
+        let regs = AA64Reg {
+            aa64pfr0: NEOVERSE_N1.aa64pfr0 & !(0xf << 20),
+            ..regs(1)
+        };
+        assert!(!parse_system_registers(regs).test(Feature::fhm as u32));
+    }
+
+    #[test]
+    fn sha3() {
+        // ID_AA64ISAR0_EL1.SHA1[11:8], SHA2[15:12] and SHA3[35:32]:
+        let regs = |sha1: u64, sha2: u64, sha3: u64| AA64Reg {
+            aa64isar0: NEOVERSE_N1.aa64isar0 & !(0xf << 8 | 0xf << 12 | 0xf << 32)
+                | sha1 << 8
+                | sha2 << 12
+                | sha3 << 32,
+            ..NEOVERSE_N1
+        };
+        let detect = |regs| {
+            let value = parse_system_registers(regs);
+            (
+                value.test(Feature::sha512 as u32),
+                value.test(Feature::sha3 as u32),
+            )
+        };
+        assert_eq!(detect(regs(1, 1, 0)), (false, false));
+        assert_eq!(detect(regs(1, 2, 0)), (true, false));
+        assert_eq!(detect(regs(1, 2, 1)), (true, true));
+        // SHA3 without SHA512, or without SHA1:
+        assert_eq!(detect(regs(1, 1, 1)), (false, false));
+        assert_eq!(detect(regs(0, 2, 1)), (false, false));
+        // The SHA extensions require SIMD support, ID_AA64PFR0_EL1.AdvSIMD[23:20]:
+        let regs = AA64Reg {
+            aa64pfr0: NEOVERSE_N1.aa64pfr0 | 0xf << 20,
+            ..regs(1, 2, 1)
+        };
+        assert_eq!(detect(regs), (false, false));
+    }
+
+    #[test]
+    fn sm4() {
+        // ID_AA64ISAR0_EL1.SM3[39:36] and SM4[43:40]:
+        let regs = |sm3: u64, sm4: u64| AA64Reg {
+            aa64isar0: NEOVERSE_N1.aa64isar0 & !(0xff << 36) | sm3 << 36 | sm4 << 40,
+            ..NEOVERSE_N1
+        };
+        let detect = |regs| parse_system_registers(regs).test(Feature::sm4 as u32);
+        assert!(!detect(regs(0, 0)));
+        assert!(!detect(regs(1, 0)));
+        assert!(!detect(regs(0, 1)));
+        assert!(detect(regs(1, 1)));
+    }
+
+    #[test]
+    fn fcma_jsconv() {
+        // ID_AA64ISAR1_EL1.JSCVT[15:12] and FCMA[19:16]:
+        let regs = |jscvt: u64, fcma: u64| AA64Reg {
+            aa64isar1: NEOVERSE_N1.aa64isar1 & !(0xff << 12) | jscvt << 12 | fcma << 16,
+            ..NEOVERSE_N1
+        };
+        let detect = |regs| {
+            let value = parse_system_registers(regs);
+            (
+                value.test(Feature::jsconv as u32),
+                value.test(Feature::fcma as u32),
+            )
+        };
+        assert_eq!(detect(regs(0, 0)), (false, false));
+        assert_eq!(detect(regs(1, 0)), (true, false));
+        assert_eq!(detect(regs(0, 1)), (false, true));
+        assert_eq!(detect(regs(1, 1)), (true, true));
+        // FCMA requires SIMD support, ID_AA64PFR0_EL1.AdvSIMD[23:20], and
+        // JSCVT requires float support, ID_AA64PFR0_EL1.FP[19:16]:
+        let no_simd = AA64Reg {
+            aa64pfr0: NEOVERSE_N1.aa64pfr0 | 0xf << 20,
+            ..regs(1, 1)
+        };
+        assert_eq!(detect(no_simd), (true, false));
+        let no_fp = AA64Reg {
+            aa64pfr0: NEOVERSE_N1.aa64pfr0 | 0xff << 16,
+            ..regs(1, 1)
+        };
+        assert_eq!(detect(no_fp), (false, false));
+    }
+
+    #[test]
+    fn dpb() {
+        // ID_AA64ISAR1_EL1.DPB[3:0]:
+        let regs = |dpb: u64| AA64Reg {
+            aa64isar1: NEOVERSE_N1.aa64isar1 & !0xf | dpb,
+            ..NEOVERSE_N1
+        };
+        let detect = |regs| {
+            let value = parse_system_registers(regs);
+            (
+                value.test(Feature::dpb as u32),
+                value.test(Feature::dpb2 as u32),
+            )
+        };
+        assert_eq!(detect(regs(0)), (false, false));
+        assert_eq!(detect(regs(1)), (true, false));
+        // DPB2 includes DPB:
+        assert_eq!(detect(regs(2)), (true, true));
+    }
+
+    #[test]
+    fn flagm() {
+        // ID_AA64ISAR0_EL1.TS[55:52]:
+        let regs = |ts: u64| AA64Reg {
+            aa64isar0: NEOVERSE_N1.aa64isar0 & !(0xf << 52) | ts << 52,
+            ..NEOVERSE_N1
+        };
+        let detect = |regs| {
+            let value = parse_system_registers(regs);
+            (
+                value.test(Feature::flagm as u32),
+                value.test(Feature::flagm2 as u32),
+            )
+        };
+        assert_eq!(detect(regs(0)), (false, false));
+        assert_eq!(detect(regs(1)), (true, false));
+        // FlagM2 includes FlagM:
+        assert_eq!(detect(regs(2)), (true, true));
+    }
+
+    #[test]
+    fn common() {
+        // A core without LSE and RDM, like a Cortex-A53 next to Armv8.2-A
+        // cores:
+        let little = AA64Reg {
+            aa64isar0: NEOVERSE_N1.aa64isar0 & !(0xf << 20 | 0xf << 28),
+            ..NEOVERSE_N1
+        };
+        let value = common_features(vec![NEOVERSE_N1, little, NEOVERSE_N1]);
+        for &f in &[Feature::lse, Feature::rdm] {
+            assert!(!value.test(f as u32), "{} detected", f.to_str());
+        }
+        for &f in &[Feature::asimd, Feature::crc, Feature::dotprod] {
+            assert!(value.test(f as u32), "{} not detected", f.to_str());
+        }
+
+        let value = common_features(vec![NEOVERSE_N1]);
+        assert!(value.test(Feature::lse as u32));
+        let value = common_features(vec![]);
+        assert!(!value.test(Feature::fp as u32));
+    }
+
+    #[test]
+    fn non_uniform_sve_features() {
+        let sve = |aa64zfr0s: &[Option<u64>]| {
+            let mut value = cache::Initializer::default();
+            value.set(Feature::sve as u32);
+            disable_non_uniform_sve(&mut value, aa64zfr0s.iter().copied());
+            value.test(Feature::sve as u32)
+        };
+        // ID_AA64ZFR0_EL1 of each core, SVE2 is SVEver[3:0] == 1:
+        assert!(sve(&[Some(0)]));
+        assert!(sve(&[Some(1), Some(1), Some(1), Some(1)]));
+        assert!(!sve(&[Some(1), Some(1), Some(0), Some(0)]));
+        assert!(!sve(&[Some(0), Some(1)]));
+        // Cores that do not implement SVE:
+        assert!(!sve(&[Some(0), None]));
+        assert!(!sve(&[None, Some(0)]));
+        assert!(!sve(&[]));
+    }
+
+    #[test]
+    fn lse2() {
+        // ID_AA64MMFR2_EL1.AT[35:32]:
+        let regs = |at: u64| AA64Reg {
+            aa64mmfr2: NEOVERSE_N1.aa64mmfr2 | at << 32,
+            ..NEOVERSE_N1
+        };
+        assert!(!parse_system_registers(regs(0)).test(Feature::lse2 as u32));
+        assert!(parse_system_registers(regs(1)).test(Feature::lse2 as u32));
+        // Only the AT field matters:
+        let regs = AA64Reg {
+            aa64mmfr2: 0xffff_fff0_ffff_ffff,
+            ..AA64Reg::default()
+        };
+        assert!(!parse_system_registers(regs).test(Feature::lse2 as u32));
+    }
+
+    #[test]
+    fn ecv() {
+        // ID_AA64MMFR0_EL1.ECV[63:60]:
+        let regs = |ecv: u64| AA64Reg {
+            aa64mmfr0: NEOVERSE_N1.aa64mmfr0 | ecv << 60,
+            ..NEOVERSE_N1
+        };
+        assert!(!parse_system_registers(regs(0)).test(Feature::ecv as u32));
+        assert!(parse_system_registers(regs(1)).test(Feature::ecv as u32));
+        // ECV == 2 adds CNTHCTL_EL2.ECV and CNTPOFF_EL2:
+        assert!(parse_system_registers(regs(2)).test(Feature::ecv as u32));
+        // Only the ECV field matters:
+        let regs = AA64Reg {
+            aa64mmfr0: 0x0fff_ffff_ffff_ffff,
+            ..AA64Reg::default()
+        };
+        assert!(!parse_system_registers(regs).test(Feature::ecv as u32));
+    }
+
+    #[test]
+    fn predres() {
+        // ID_AA64ISAR1_EL1.SPECRES[43:40]:
+        let regs = |specres: u64| AA64Reg {
+            aa64isar1: NEOVERSE_N1.aa64isar1 | specres << 40,
+            ..NEOVERSE_N1
+        };
+        assert!(!parse_system_registers(NEOVERSE_N1).test(Feature::predres as u32));
+        assert!(parse_system_registers(regs(1)).test(Feature::predres as u32));
+        // SPECRES == 2 adds COSP RCTX:
+        assert!(parse_system_registers(regs(2)).test(Feature::predres as u32));
+        // Only the SPECRES field matters:
+        let regs = AA64Reg {
+            aa64isar1: !(0xf << 40),
+            ..AA64Reg::default()
+        };
+        assert!(!parse_system_registers(regs).test(Feature::predres as u32));
+    }
+}
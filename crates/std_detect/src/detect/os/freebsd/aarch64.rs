@@ -1,4 +1,8 @@
 //! Run-time feature detection for Aarch64 on FreeBSD.
+//!
+//! FreeBSD emulates the mrs instruction and only reports the features that
+//! are supported by every core, so unlike on NetBSD the registers of each
+//! core do not have to be read.
 
 pub use super::super::aarch64::detect_features;
 
@@ -18,5 +22,27 @@ mod tests {
         println!("rcpc: {:?}", is_aarch64_feature_detected!("rcpc"));
         println!("dotprod: {:?}", is_aarch64_feature_detected!("dotprod"));
         println!("tme: {:?}", is_aarch64_feature_detected!("tme"));
+        println!("lse2: {:?}", is_aarch64_feature_detected!("lse2"));
+        println!("fhm: {:?}", is_aarch64_feature_detected!("fhm"));
+        println!("sha512: {:?}", is_aarch64_feature_detected!("sha512"));
+        println!("sha3: {:?}", is_aarch64_feature_detected!("sha3"));
+        println!("sm4: {:?}", is_aarch64_feature_detected!("sm4"));
+        println!("fcma: {:?}", is_aarch64_feature_detected!("fcma"));
+        println!("jsconv: {:?}", is_aarch64_feature_detected!("jsconv"));
+        println!("dpb: {:?}", is_aarch64_feature_detected!("dpb"));
+        println!("dpb2: {:?}", is_aarch64_feature_detected!("dpb2"));
+        println!("flagm: {:?}", is_aarch64_feature_detected!("flagm"));
+        println!("flagm2: {:?}", is_aarch64_feature_detected!("flagm2"));
+        println!("aes: {:?}", is_aarch64_feature_detected!("aes"));
+        println!("ecv: {:?}", is_aarch64_feature_detected!("ecv"));
+        println!("rprfm: {:?}", is_aarch64_feature_detected!("rprfm"));
+        println!("sve2: {:?}", is_aarch64_feature_detected!("sve2"));
+        println!("sve2p1: {:?}", is_aarch64_feature_detected!("sve2p1"));
+        println!("sme: {:?}", is_aarch64_feature_detected!("sme"));
+        println!("sme2: {:?}", is_aarch64_feature_detected!("sme2"));
+        println!("sme2p1: {:?}", is_aarch64_feature_detected!("sme2p1"));
+        println!("faminmax: {:?}", is_aarch64_feature_detected!("faminmax"));
+        println!("lut: {:?}", is_aarch64_feature_detected!("lut"));
+        println!("predres: {:?}", is_aarch64_feature_detected!("predres"));
     }
 }
@@ -13,6 +13,7 @@ pub(crate) fn detect_features() -> cache::Initializer {
     };
 
     if let Ok(auxv) = auxvec::auxv() {
+        cache::set_detection_source(cache::DetectionSource::Hwcap);
         enable_feature(&mut value, Feature::neon, auxv.hwcap & 0x00001000 != 0);
         enable_feature(&mut value, Feature::pmull, auxv.hwcap2 & 0x00000002 != 0);
         return value;
@@ -1,21 +1,83 @@
 //! Run-time feature detection for PowerPC on FreeBSD.
 
 use super::auxvec;
-use crate::detect::{cache, Feature};
+use crate::detect::{bit, cache, Feature};
 
-pub(crate) fn detect_features() -> cache::Initializer {
-    let mut value = cache::Initializer::default();
-    let enable_feature = |value: &mut cache::Initializer, f, enable| {
-        if enable {
-            value.set(f as u32);
-        }
-    };
+// The values are part of the platform-specific [machine/cpu.h][cpu_h], and
+// match those of Linux.
+//
+// [cpu_h]: https://github.com/freebsd/freebsd-src/blob/main/sys/powerpc/include/cpu.h
+//
+// note: cpu.h defines the values as the mask to do the test (e.g.
+// `PPC_FEATURE_HAS_ALTIVEC` is `0x10000000`); these are the indices of the
+// bits to test.
+const PPC_FEATURE_HAS_ALTIVEC: u32 = 28;
+const PPC_FEATURE_HAS_VSX: u32 = 7;
+const PPC_FEATURE2_ARCH_2_07: u32 = 31;
+const PPC_FEATURE2_ARCH_3_00: u32 = 23;
+const PPC_FEATURE2_ARCH_3_1: u32 = 18;
+
+/// The `AT_HWCAP` bits that map directly to a feature.
+const HWCAP_FEATURES: &[(u32, Feature)] = &[
+    (PPC_FEATURE_HAS_ALTIVEC, Feature::altivec),
+    (PPC_FEATURE_HAS_VSX, Feature::vsx),
+];
+
+/// The `AT_HWCAP2` bits that map directly to a feature.
+const HWCAP2_FEATURES: &[(u32, Feature)] = &[
+    (PPC_FEATURE2_ARCH_2_07, Feature::power8),
+    (PPC_FEATURE2_ARCH_3_00, Feature::power9),
+    (PPC_FEATURE2_ARCH_3_1, Feature::power10),
+];
 
+/// Try to read the features from the auxiliary vector.
+pub(crate) fn detect_features() -> cache::Initializer {
     if let Ok(auxv) = auxvec::auxv() {
-        enable_feature(&mut value, Feature::altivec, auxv.hwcap & 0x10000000 != 0);
-        enable_feature(&mut value, Feature::vsx, auxv.hwcap & 0x00000080 != 0);
-        enable_feature(&mut value, Feature::power8, auxv.hwcap2 & 0x80000000 != 0);
-        return value;
+        cache::set_detection_source(cache::DetectionSource::Hwcap);
+        return hwcap_features(auxv.hwcap, auxv.hwcap2);
     }
+    cache::Initializer::default()
+}
+
+/// Computes the features from the `AT_HWCAP` and `AT_HWCAP2` bitfields.
+fn hwcap_features(hwcap: usize, hwcap2: usize) -> cache::Initializer {
+    let mut value = cache::Initializer::default();
+    bit::enable_features(&mut value, hwcap, HWCAP_FEATURES);
+    bit::enable_features(&mut value, hwcap2, HWCAP2_FEATURES);
     value
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(hwcap: usize, hwcap2: usize) -> [bool; 5] {
+        let value = hwcap_features(hwcap, hwcap2);
+        [
+            value.test(Feature::altivec as u32),
+            value.test(Feature::vsx as u32),
+            value.test(Feature::power8 as u32),
+            value.test(Feature::power9 as u32),
+            value.test(Feature::power10 as u32),
+        ]
+    }
+
+    #[test]
+    fn hwcap() {
+        // PPC_FEATURE_64 | PPC_FEATURE_HAS_FPU | PPC_FEATURE_HAS_MMU:
+        let base = 0x4000_0000 | 0x0800_0000 | 0x0400_0000;
+        assert_eq!(features(base, 0), [false; 5]);
+        // POWER7: PPC_FEATURE_HAS_ALTIVEC | PPC_FEATURE_HAS_VSX.
+        let power7 = base | 0x1000_0000 | 0x0000_0080;
+        assert_eq!(features(power7, 0), [true, true, false, false, false]);
+        // POWER8: PPC_FEATURE2_ARCH_2_07 | PPC_FEATURE2_HAS_HTM.
+        let power8 = 0x8000_0000 | 0x4000_0000;
+        assert_eq!(features(power7, power8), [true, true, true, false, false]);
+        // POWER9: PPC_FEATURE2_ARCH_3_00 | PPC_FEATURE2_DARN.
+        let power9 = power8 | 0x0080_0000 | 0x0020_0000;
+        assert_eq!(features(power7, power9), [true, true, true, true, false]);
+        // POWER10: PPC_FEATURE2_ARCH_3_1.
+        let power10 = power9 | 0x0004_0000;
+        assert_eq!(features(power7, power10), [true; 5]);
+    }
+}
@@ -1,80 +1,164 @@
 //! Run-time feature detection for Aarch64 on Linux.
 
 use super::auxvec;
+use crate::detect::aarch64::{parse_system_registers, AA64Reg};
 use crate::detect::{bit, cache, Feature};
 
 /// Try to read the features from the auxiliary vector, and if that fails, try
 /// to read them from /proc/cpuinfo.
 pub(crate) fn detect_features() -> cache::Initializer {
     if let Ok(auxv) = auxvec::auxv() {
+        cache::set_detection_source(cache::DetectionSource::Hwcap);
         let hwcap: AtHwcap = auxv.into();
-        return hwcap.cache();
+        return decode_aarch64_features(hwcap.0, auxv.hwcap2, None);
     }
     #[cfg(feature = "std_detect_file_io")]
     if let Ok(c) = super::cpuinfo::CpuInfo::new() {
+        cache::set_detection_source(cache::DetectionSource::CpuInfo);
         let hwcap: AtHwcap = c.into();
-        return hwcap.cache();
+        // Only the `AT_HWCAP` features are read from `/proc/cpuinfo`.
+        return decode_aarch64_features(hwcap.0, 0, None);
     }
     cache::Initializer::default()
 }
 
-/// These values are part of the platform-specific [asm/hwcap.h][hwcap] .
+/// Decodes the features from the `AT_HWCAP` and `AT_HWCAP2` bitfields, or
+/// from the values of the system registers if `regs` is given.
 ///
-/// [hwcap]: https://github.com/torvalds/linux/blob/master/arch/arm64/include/uapi/asm/hwcap.h
-struct AtHwcap {
-    fp: bool,    // 0
-    asimd: bool, // 1
-    // evtstrm: bool, // 2
-    aes: bool,     // 3
-    pmull: bool,   // 4
-    sha1: bool,    // 5
-    sha2: bool,    // 6
-    crc32: bool,   // 7
-    atomics: bool, // 8
-    fphp: bool,    // 9
-    asimdhp: bool, // 10
-    // cpuid: bool, // 11
-    asimdrdm: bool, // 12
-    // jscvt: bool, // 13
-    // fcma: bool, // 14
-    lrcpc: bool, // 15
-    // dcpop: bool, // 16
-    // sha3: bool, // 17
-    // sm3: bool, // 18
-    // sm4: bool, // 19
-    asimddp: bool, // 20
-    // sha512: bool, // 21
-    sve: bool, // 22
+/// This performs no system calls, so recorded values can be decoded, and the
+/// features reported by the two paths compared against each other.
+pub(crate) fn decode_aarch64_features(
+    hwcap: usize,
+    hwcap2: usize,
+    regs: Option<AA64Reg>,
+) -> cache::Initializer {
+    if let Some(regs) = regs {
+        return parse_system_registers(regs);
+    }
+    let mut value = AtHwcap(hwcap).cache();
+    bit::enable_features(&mut value, hwcap2, HWCAP2_FEATURES);
+    value
 }
 
+// These values are part of the platform-specific [asm/hwcap.h][hwcap].
+//
+// [hwcap]: https://github.com/torvalds/linux/blob/master/arch/arm64/include/uapi/asm/hwcap.h
+const HWCAP_FP: u32 = 0;
+const HWCAP_ASIMD: u32 = 1;
+const HWCAP_EVTSTRM: u32 = 2;
+const HWCAP_AES: u32 = 3;
+const HWCAP_PMULL: u32 = 4;
+const HWCAP_SHA1: u32 = 5;
+const HWCAP_SHA2: u32 = 6;
+const HWCAP_CRC32: u32 = 7;
+const HWCAP_ATOMICS: u32 = 8;
+const HWCAP_FPHP: u32 = 9;
+const HWCAP_ASIMDHP: u32 = 10;
+const HWCAP_CPUID: u32 = 11;
+const HWCAP_ASIMDRDM: u32 = 12;
+const HWCAP_JSCVT: u32 = 13;
+const HWCAP_FCMA: u32 = 14;
+const HWCAP_LRCPC: u32 = 15;
+const HWCAP_DCPOP: u32 = 16;
+const HWCAP_SHA3: u32 = 17;
+const HWCAP_SM3: u32 = 18;
+const HWCAP_SM4: u32 = 19;
+const HWCAP_ASIMDDP: u32 = 20;
+const HWCAP_SHA512: u32 = 21;
+const HWCAP_SVE: u32 = 22;
+const HWCAP_ASIMDFHM: u32 = 23;
+const HWCAP_DIT: u32 = 24;
+const HWCAP_USCAT: u32 = 25;
+const HWCAP_ILRCPC: u32 = 26;
+const HWCAP_FLAGM: u32 = 27;
+const HWCAP_SSBS: u32 = 28;
+const HWCAP_SB: u32 = 29;
+const HWCAP_PACA: u32 = 30;
+const HWCAP_PACG: u32 = 31;
+
+const HWCAP2_DCPODP: u32 = 0;
+const HWCAP2_SVE2: u32 = 1;
+const HWCAP2_FLAGM2: u32 = 7;
+const HWCAP2_ECV: u32 = 19;
+const HWCAP2_SME: u32 = 23;
+const HWCAP2_RPRFM: u32 = 35;
+const HWCAP2_SVE2P1: u32 = 36;
+const HWCAP2_SME2: u32 = 37;
+const HWCAP2_SME2P1: u32 = 38;
+const HWCAP2_LUT: u32 = 49;
+const HWCAP2_FAMINMAX: u32 = 50;
+
+/// The `AT_HWCAP` bits, and the names used for them in the `Features` field
+/// of `/proc/cpuinfo`.
+const HWCAP_NAMES: &[(u32, &str)] = &[
+    (HWCAP_FP, "fp"),
+    (HWCAP_ASIMD, "asimd"),
+    (HWCAP_EVTSTRM, "evtstrm"),
+    (HWCAP_AES, "aes"),
+    (HWCAP_PMULL, "pmull"),
+    (HWCAP_SHA1, "sha1"),
+    (HWCAP_SHA2, "sha2"),
+    (HWCAP_CRC32, "crc32"),
+    (HWCAP_ATOMICS, "atomics"),
+    (HWCAP_FPHP, "fphp"),
+    (HWCAP_ASIMDHP, "asimdhp"),
+    (HWCAP_CPUID, "cpuid"),
+    (HWCAP_ASIMDRDM, "asimdrdm"),
+    (HWCAP_JSCVT, "jscvt"),
+    (HWCAP_FCMA, "fcma"),
+    (HWCAP_LRCPC, "lrcpc"),
+    (HWCAP_DCPOP, "dcpop"),
+    (HWCAP_SHA3, "sha3"),
+    (HWCAP_SM3, "sm3"),
+    (HWCAP_SM4, "sm4"),
+    (HWCAP_ASIMDDP, "asimddp"),
+    (HWCAP_SHA512, "sha512"),
+    (HWCAP_SVE, "sve"),
+    (HWCAP_ASIMDFHM, "asimdfhm"),
+    (HWCAP_DIT, "dit"),
+    (HWCAP_USCAT, "uscat"),
+    (HWCAP_ILRCPC, "ilrcpc"),
+    (HWCAP_FLAGM, "flagm"),
+    (HWCAP_SSBS, "ssbs"),
+    (HWCAP_SB, "sb"),
+    (HWCAP_PACA, "paca"),
+    (HWCAP_PACG, "pacg"),
+];
+
+/// The `AT_HWCAP` bits that map directly to a feature.
+const HWCAP_FEATURES: &[(u32, Feature)] = &[
+    (HWCAP_AES, Feature::aes),
+    (HWCAP_PMULL, Feature::pmull),
+    (HWCAP_CRC32, Feature::crc),
+    (HWCAP_ATOMICS, Feature::lse),
+    (HWCAP_LRCPC, Feature::rcpc),
+    (HWCAP_USCAT, Feature::lse2),
+    (HWCAP_DCPOP, Feature::dpb),
+    (HWCAP_FLAGM, Feature::flagm),
+];
+
+/// The `AT_HWCAP2` bits that map directly to a feature.
+const HWCAP2_FEATURES: &[(u32, Feature)] = &[
+    (HWCAP2_DCPODP, Feature::dpb2),
+    (HWCAP2_SVE2, Feature::sve2),
+    (HWCAP2_FLAGM2, Feature::flagm2),
+    (HWCAP2_ECV, Feature::ecv),
+    (HWCAP2_SME, Feature::sme),
+    (HWCAP2_RPRFM, Feature::rprfm),
+    (HWCAP2_SVE2P1, Feature::sve2p1),
+    (HWCAP2_SME2, Feature::sme2),
+    (HWCAP2_SME2P1, Feature::sme2p1),
+    (HWCAP2_LUT, Feature::lut),
+    (HWCAP2_FAMINMAX, Feature::faminmax),
+];
+
+/// The `AT_HWCAP` bitfield.
+struct AtHwcap(usize);
+
 impl From<auxvec::AuxVec> for AtHwcap {
     /// Reads AtHwcap from the auxiliary vector.
     fn from(auxv: auxvec::AuxVec) -> Self {
-        AtHwcap {
-            fp: bit::test(auxv.hwcap, 0),
-            asimd: bit::test(auxv.hwcap, 1),
-            // evtstrm: bit::test(auxv.hwcap, 2),
-            aes: bit::test(auxv.hwcap, 3),
-            pmull: bit::test(auxv.hwcap, 4),
-            sha1: bit::test(auxv.hwcap, 5),
-            sha2: bit::test(auxv.hwcap, 6),
-            crc32: bit::test(auxv.hwcap, 7),
-            atomics: bit::test(auxv.hwcap, 8),
-            fphp: bit::test(auxv.hwcap, 9),
-            asimdhp: bit::test(auxv.hwcap, 10),
-            // cpuid: bit::test(auxv.hwcap, 11),
-            asimdrdm: bit::test(auxv.hwcap, 12),
-            // jscvt: bit::test(auxv.hwcap, 13),
-            // fcma: bit::test(auxv.hwcap, 14),
-            lrcpc: bit::test(auxv.hwcap, 15),
-            // dcpop: bit::test(auxv.hwcap, 16),
-            // sha3: bit::test(auxv.hwcap, 17),
-            // sm3: bit::test(auxv.hwcap, 18),
-            // sm4: bit::test(auxv.hwcap, 19),
-            asimddp: bit::test(auxv.hwcap, 20),
-            // sha512: bit::test(auxv.hwcap, 21),
-            sve: bit::test(auxv.hwcap, 22),
-        }
+        AtHwcap(auxv.hwcap)
     }
 }
 
@@ -83,44 +167,32 @@ impl From<super::cpuinfo::CpuInfo> for AtHwcap {
     /// Reads AtHwcap from /proc/cpuinfo .
     fn from(c: super::cpuinfo::CpuInfo) -> Self {
         let f = &c.field("Features");
-        AtHwcap {
-            // 64-bit names. FIXME: In 32-bit compatibility mode /proc/cpuinfo will
-            // map some of the 64-bit names to some 32-bit feature names. This does not
-            // cover that yet.
-            fp: f.has("fp"),
-            asimd: f.has("asimd"),
-            // evtstrm: f.has("evtstrm"),
-            aes: f.has("aes"),
-            pmull: f.has("pmull"),
-            sha1: f.has("sha1"),
-            sha2: f.has("sha2"),
-            crc32: f.has("crc32"),
-            atomics: f.has("atomics"),
-            fphp: f.has("fphp"),
-            asimdhp: f.has("asimdhp"),
-            // cpuid: f.has("cpuid"),
-            asimdrdm: f.has("asimdrdm"),
-            // jscvt: f.has("jscvt"),
-            // fcma: f.has("fcma"),
-            lrcpc: f.has("lrcpc"),
-            // dcpop: f.has("dcpop"),
-            // sha3: f.has("sha3"),
-            // sm3: f.has("sm3"),
-            // sm4: f.has("sm4"),
-            asimddp: f.has("asimddp"),
-            // sha512: f.has("sha512"),
-            sve: f.has("sve"),
+        // 64-bit names. FIXME: In 32-bit compatibility mode /proc/cpuinfo will
+        // map some of the 64-bit names to some 32-bit feature names. This does not
+        // cover that yet.
+        let mut hwcap = 0;
+        for &(bit, name) in HWCAP_NAMES {
+            if f.has(name) {
+                hwcap |= 1 << bit;
+            }
         }
+        AtHwcap(hwcap)
     }
 }
 
 impl AtHwcap {
+    /// Is the `bit` of `AT_HWCAP` set?
+    fn has(&self, bit: u32) -> bool {
+        bit::test(self.0, bit)
+    }
+
     /// Initializes the cache from the feature -bits.
     ///
     /// The features are enabled approximately like in LLVM host feature detection:
     /// https://github.com/llvm-mirror/llvm/blob/master/lib/Support/Host.cpp#L1273
     fn cache(self) -> cache::Initializer {
         let mut value = cache::Initializer::default();
+        bit::enable_features(&mut value, self.0, HWCAP_FEATURES);
         {
             let mut enable_feature = |f, enable| {
                 if enable {
@@ -128,29 +200,392 @@ impl AtHwcap {
                 }
             };
 
-            enable_feature(Feature::fp, self.fp);
+            let fp = self.has(HWCAP_FP);
+            let fphp = self.has(HWCAP_FPHP);
+            enable_feature(Feature::fp, fp);
             // Half-float support requires float support
-            enable_feature(Feature::fp16, self.fp && self.fphp);
-            enable_feature(Feature::pmull, self.pmull);
-            enable_feature(Feature::crc, self.crc32);
-            enable_feature(Feature::lse, self.atomics);
-            enable_feature(Feature::rcpc, self.lrcpc);
+            enable_feature(Feature::fp16, fp && fphp);
 
             // SIMD support requires float support - if half-floats are
             // supported, it also requires half-float support:
-            let asimd = self.fp && self.asimd && (!self.fphp | self.asimdhp);
+            let asimd = fp && self.has(HWCAP_ASIMD) && (!fphp | self.has(HWCAP_ASIMDHP));
             enable_feature(Feature::asimd, asimd);
             // SIMD extensions require SIMD support:
-            enable_feature(Feature::rdm, self.asimdrdm && asimd);
-            enable_feature(Feature::dotprod, self.asimddp && asimd);
-            enable_feature(Feature::sve, self.sve && asimd);
+            enable_feature(Feature::rdm, self.has(HWCAP_ASIMDRDM) && asimd);
+            enable_feature(Feature::dotprod, self.has(HWCAP_ASIMDDP) && asimd);
+            enable_feature(Feature::sve, self.has(HWCAP_SVE) && asimd);
+            // SHA512 and SHA3 are only reported together with the rest of the
+            // SHA extensions:
+            let sha512 =
+                asimd && self.has(HWCAP_SHA1) && self.has(HWCAP_SHA2) && self.has(HWCAP_SHA512);
+            enable_feature(Feature::sha512, sha512);
+            enable_feature(Feature::sha3, sha512 && self.has(HWCAP_SHA3));
+            enable_feature(Feature::jsconv, self.has(HWCAP_JSCVT) && fp);
+            enable_feature(Feature::fcma, self.has(HWCAP_FCMA) && asimd);
+            enable_feature(
+                Feature::sm4,
+                asimd && self.has(HWCAP_SM3) && self.has(HWCAP_SM4),
+            );
+            // FHM also requires half-float SIMD support:
+            enable_feature(
+                Feature::fhm,
+                self.has(HWCAP_ASIMDFHM) && asimd && fphp && self.has(HWCAP_ASIMDHP),
+            );
 
             // Crypto is specified as AES + PMULL + SHA1 + SHA2 per LLVM/hosts.cpp
             enable_feature(
                 Feature::crypto,
-                self.aes && self.pmull && self.sha1 && self.sha2,
+                self.has(HWCAP_AES)
+                    && self.has(HWCAP_PMULL)
+                    && self.has(HWCAP_SHA1)
+                    && self.has(HWCAP_SHA2),
             );
         }
         value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `AT_HWCAP` of a Cortex-A53: `fp asimd evtstrm aes pmull sha1 sha2 crc32`.
+    const CORTEX_A53: usize = 0xff;
+
+    #[test]
+    fn hwcap_detection_source() {
+        // The source is recorded when the cache is initialized:
+        if auxvec::auxv().is_ok() && cache::test(Feature::asimd as u32) {
+            assert_eq!(cache::detection_source(), cache::DetectionSource::Hwcap);
+        }
+    }
+
+    #[test]
+    fn cortex_a53() {
+        // These are the features that were reported for this `AT_HWCAP`
+        // before the bits were decoded through tables.
+        let value = AtHwcap(CORTEX_A53).cache();
+        for &f in &[
+            Feature::fp,
+            Feature::asimd,
+            Feature::aes,
+            Feature::pmull,
+            Feature::crc,
+            Feature::crypto,
+        ] {
+            assert!(value.test(f as u32), "{} not detected", f.to_str());
+        }
+        for &f in &[
+            Feature::fp16,
+            Feature::sve,
+            Feature::lse,
+            Feature::rdm,
+            Feature::rcpc,
+            Feature::dotprod,
+            Feature::tme,
+        ] {
+            assert!(!value.test(f as u32), "{} detected", f.to_str());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std_detect_file_io")]
+    fn cortex_a53_cpuinfo() {
+        let cpuinfo = super::super::cpuinfo::CpuInfo::from_str(
+            "Features\t: fp asimd evtstrm aes pmull sha1 sha2 crc32\n",
+        )
+        .unwrap();
+        let hwcap: AtHwcap = cpuinfo.into();
+        assert_eq!(hwcap.0, CORTEX_A53);
+    }
+
+    /// Feeds the same capability, CRC32 on a core with FP and AdvSIMD,
+    /// through each decoder, which must report exactly the same features.
+    #[test]
+    fn crc_agrees_with_system_registers() {
+        for &crc in &[false, true] {
+            // ID_AA64PFR0_EL1 is 0: FP and AdvSIMD are implemented.
+            let regs = parse_system_registers(AA64Reg {
+                aa64isar0: (crc as u64) << 16,
+                ..AA64Reg::default()
+            });
+            assert_eq!(regs.test(Feature::crc as u32), crc);
+
+            let hwcap = AtHwcap((crc as usize) << HWCAP_CRC32).cache();
+            assert_eq!(hwcap.test(Feature::crc as u32), crc);
+
+            let hwcap = (1 << HWCAP_FP) | (1 << HWCAP_ASIMD) | (crc as usize) << HWCAP_CRC32;
+            assert_eq!(AtHwcap(hwcap).cache(), regs);
+            assert_eq!(decode_aarch64_features(hwcap, 0, None), regs);
+
+            #[cfg(feature = "std_detect_file_io")]
+            {
+                let features = if crc { "fp asimd crc32" } else { "fp asimd" };
+                let cpuinfo = format!("Features\t: {}\n", features);
+                let cpuinfo = super::super::cpuinfo::CpuInfo::from_str(&cpuinfo).unwrap();
+                let hwcap: AtHwcap = cpuinfo.into();
+                assert_eq!(hwcap.cache(), regs);
+            }
+        }
+    }
+
+    #[test]
+    fn rdm_agrees_with_system_registers() {
+        for &rdm in &[false, true] {
+            let hwcap = (1 << HWCAP_FP) | (1 << HWCAP_ASIMD) | (rdm as usize) << HWCAP_ASIMDRDM;
+            let hwcap = AtHwcap(hwcap).cache();
+            // ID_AA64PFR0_EL1 is 0: FP and AdvSIMD are implemented.
+            let regs = parse_system_registers(AA64Reg {
+                aa64isar0: (rdm as u64) << 28,
+                ..AA64Reg::default()
+            });
+            assert_eq!(hwcap.test(Feature::rdm as u32), rdm);
+            assert_eq!(regs.test(Feature::rdm as u32), rdm);
+        }
+        // Neither path reports RDM without SIMD support.
+        let hwcap = AtHwcap((1 << HWCAP_FP) | (1 << HWCAP_ASIMDRDM)).cache();
+        let regs = parse_system_registers(AA64Reg {
+            aa64isar0: 1 << 28,
+            aa64pfr0: 0xf << 20,
+            ..AA64Reg::default()
+        });
+        assert!(!hwcap.test(Feature::rdm as u32));
+        assert!(!regs.test(Feature::rdm as u32));
+    }
+
+    #[test]
+    fn dotprod_agrees_with_system_registers() {
+        for &dotprod in &[false, true] {
+            let hwcap = (1 << HWCAP_FP) | (1 << HWCAP_ASIMD) | (dotprod as usize) << HWCAP_ASIMDDP;
+            let hwcap = AtHwcap(hwcap).cache();
+            // ID_AA64PFR0_EL1 is 0: FP and AdvSIMD are implemented.
+            let regs = parse_system_registers(AA64Reg {
+                aa64isar0: (dotprod as u64) << 44,
+                ..AA64Reg::default()
+            });
+            assert_eq!(hwcap.test(Feature::dotprod as u32), dotprod);
+            assert_eq!(regs.test(Feature::dotprod as u32), dotprod);
+        }
+    }
+
+    #[test]
+    fn sha3_agrees_with_system_registers() {
+        // (SHA2 field of ID_AA64ISAR0_EL1, SHA512, SHA3)
+        for &(sha2, sha512, sha3) in &[
+            (1, false, false),
+            (1, false, true),
+            (2, true, false),
+            (2, true, true),
+        ] {
+            let mut hwcap =
+                (1 << HWCAP_FP) | (1 << HWCAP_ASIMD) | (1 << HWCAP_SHA1) | (1 << HWCAP_SHA2);
+            hwcap |= (sha512 as usize) << HWCAP_SHA512 | (sha3 as usize) << HWCAP_SHA3;
+            let hwcap = AtHwcap(hwcap).cache();
+            let regs = parse_system_registers(AA64Reg {
+                aa64isar0: 1 << 8 | sha2 << 12 | (sha3 as u64) << 32,
+                ..AA64Reg::default()
+            });
+            for value in &[hwcap, regs] {
+                assert_eq!(value.test(Feature::sha512 as u32), sha512);
+                assert_eq!(value.test(Feature::sha3 as u32), sha512 && sha3);
+            }
+        }
+    }
+
+    #[test]
+    fn ecv_agrees_with_system_registers() {
+        for &ecv in &[false, true] {
+            let hwcap2 = decode_aarch64_features(0, (ecv as usize) << HWCAP2_ECV, None);
+            let regs = parse_system_registers(AA64Reg {
+                aa64mmfr0: (ecv as u64) << 60,
+                ..AA64Reg::default()
+            });
+            assert_eq!(hwcap2.test(Feature::ecv as u32), ecv);
+            assert_eq!(regs.test(Feature::ecv as u32), ecv);
+        }
+    }
+
+    #[test]
+    fn hwcap2_rprfm() {
+        let value = decode_aarch64_features(0, 1 << HWCAP2_RPRFM, None);
+        assert!(value.test(Feature::rprfm as u32));
+        assert!(!value.test(Feature::ecv as u32));
+        // The neighbouring `AT_HWCAP2` bits are not `rprfm`:
+        let value = decode_aarch64_features(0, 0x0000_0014_0000_0000, None);
+        assert!(!value.test(Feature::rprfm as u32));
+        // Nor are the `AT_HWCAP` bits:
+        let value = decode_aarch64_features(!0, 0, None);
+        assert!(!value.test(Feature::rprfm as u32));
+    }
+
+    #[test]
+    fn hwcap2_sve2p1_sme2p1() {
+        let bits = [
+            (HWCAP2_SVE2, Feature::sve2),
+            (HWCAP2_SVE2P1, Feature::sve2p1),
+            (HWCAP2_SME, Feature::sme),
+            (HWCAP2_SME2, Feature::sme2),
+            (HWCAP2_SME2P1, Feature::sme2p1),
+        ];
+        // Each bit only enables its own feature; the implied features are
+        // only enabled once the implications are expanded:
+        for &(bit, f) in &bits {
+            let value = decode_aarch64_features(0, 1 << bit, None);
+            for &(_, g) in &bits {
+                assert_eq!(value.test(g as u32), f as u32 == g as u32);
+            }
+            assert!(!value.test(Feature::sve as u32));
+        }
+    }
+
+    #[test]
+    fn hwcap2_faminmax_lut() {
+        let detected = |hwcap2: usize| {
+            let value = decode_aarch64_features(0, hwcap2, None);
+            (
+                value.test(Feature::faminmax as u32),
+                value.test(Feature::lut as u32),
+            )
+        };
+        assert_eq!(detected(1 << HWCAP2_FAMINMAX), (true, false));
+        assert_eq!(detected(1 << HWCAP2_LUT), (false, true));
+        // `fpmr` and `f8cvt`, on either side, are not detected:
+        assert_eq!(detected(0x0009_0000_0000_0000), (false, false));
+        // The bits that are reserved, or not mapped to a feature, are ignored:
+        let unknown = !((1 << HWCAP2_FAMINMAX) | (1 << HWCAP2_LUT));
+        assert_eq!(detected(unknown), (false, false));
+        assert_eq!(detected(!0), (true, true));
+    }
+
+    /// `/proc/cpuinfo` of a Neoverse N1 (AWS Graviton2) with two cores.
+    #[cfg(feature = "std_detect_file_io")]
+    const NEOVERSE_N1: &str = r"processor	: 0
+BogoMIPS	: 243.75
+Features	: fp asimd evtstrm aes pmull sha1 sha2 crc32 atomics fphp asimdhp cpuid asimdrdm lrcpc dcpop asimddp ssbs
+CPU implementer	: 0x41
+CPU architecture: 8
+CPU variant	: 0x3
+CPU part	: 0xd0c
+CPU revision	: 1
+
+processor	: 1
+BogoMIPS	: 243.75
+Features	: fp asimd evtstrm aes pmull sha1 sha2 crc32 atomics fphp asimdhp cpuid asimdrdm lrcpc dcpop asimddp ssbs
+CPU implementer	: 0x41
+CPU architecture: 8
+CPU variant	: 0x3
+CPU part	: 0xd0c
+CPU revision	: 1
+";
+
+    #[test]
+    #[cfg(feature = "std_detect_file_io")]
+    fn neoverse_n1_cpuinfo() {
+        let cpuinfo = super::super::cpuinfo::CpuInfo::from_str(NEOVERSE_N1).unwrap();
+        let hwcap: AtHwcap = cpuinfo.into();
+        let value = hwcap.cache();
+        for &f in &[
+            Feature::fp,
+            Feature::fp16,
+            Feature::asimd,
+            Feature::aes,
+            Feature::pmull,
+            Feature::crc,
+            Feature::crypto,
+            Feature::lse,
+            Feature::rdm,
+            Feature::rcpc,
+            Feature::dotprod,
+            Feature::dpb,
+        ] {
+            assert!(value.test(f as u32), "{} not detected", f.to_str());
+        }
+        for &f in &[
+            Feature::sve,
+            Feature::tme,
+            Feature::lse2,
+            Feature::fhm,
+            Feature::sha512,
+            Feature::sha3,
+            Feature::sm4,
+            Feature::fcma,
+            Feature::jsconv,
+            Feature::dpb2,
+            Feature::flagm,
+            Feature::flagm2,
+        ] {
+            assert!(!value.test(f as u32), "{} detected", f.to_str());
+        }
+    }
+
+    /// Asserts that the HWCAP and the system register paths decode the same
+    /// features.
+    fn assert_paths_agree(hwcap: usize, hwcap2: usize, regs: AA64Reg) {
+        let from_hwcap = decode_aarch64_features(hwcap, hwcap2, None);
+        let from_regs = decode_aarch64_features(0, 0, Some(regs));
+        for i in 0..Feature::_last as u32 {
+            assert_eq!(from_hwcap.test(i), from_regs.test(i), "feature {}", i);
+        }
+    }
+
+    #[test]
+    fn decode_neoverse_n1() {
+        // AWS Graviton2, see `NEOVERSE_N1`:
+        let hwcap = 0x1011_9fff;
+        let regs = AA64Reg {
+            aa64isar0: 0x0000_1000_1021_1120,
+            aa64isar1: 0x0000_0000_0010_0001,
+            aa64mmfr0: 0x0000_0000_0010_1125,
+            aa64mmfr2: 0x0000_0000_0000_1011,
+            aa64pfr0: 0x1100_0000_1011_1111,
+        };
+        assert_paths_agree(hwcap, 0, regs);
+        let value = decode_aarch64_features(hwcap, 0, None);
+        for &f in &[Feature::fp16, Feature::dotprod, Feature::rcpc, Feature::dpb] {
+            assert!(value.test(f as u32), "{} not detected", f.to_str());
+        }
+    }
+
+    #[test]
+    fn decode_cortex_a72() {
+        // Raspberry Pi 4, without the crypto extension:
+        // `fp asimd evtstrm crc32 cpuid`.
+        let hwcap = 0x887;
+        let regs = AA64Reg {
+            aa64isar0: 0x0000_0000_0001_0000,
+            aa64pfr0: 0x0000_0000_0000_2222,
+            ..AA64Reg::default()
+        };
+        assert_paths_agree(hwcap, 0, regs);
+        let value = decode_aarch64_features(hwcap, 0, None);
+        assert!(value.test(Feature::crc as u32));
+        assert!(!value.test(Feature::aes as u32));
+    }
+
+    #[test]
+    fn decode_neoverse_v1() {
+        // AWS Graviton3: every `AT_HWCAP` bit except `sb`, and
+        // `dcpodp svei8mm svebf16 i8mm bf16 dgh rng` in `AT_HWCAP2`.
+        let value = decode_aarch64_features(0xdfff_ffff, 0x1_f201, None);
+        for &f in &[
+            Feature::sve,
+            Feature::sha512,
+            Feature::sha3,
+            Feature::sm4,
+            Feature::fhm,
+            Feature::fcma,
+            Feature::jsconv,
+            Feature::lse2,
+            Feature::flagm,
+            Feature::dpb2,
+        ] {
+            assert!(value.test(f as u32), "{} not detected", f.to_str());
+        }
+        for &f in &[Feature::flagm2, Feature::tme] {
+            assert!(!value.test(f as u32), "{} detected", f.to_str());
+        }
+        // `AT_HWCAP2` is the only source of `dpb2`:
+        let value = decode_aarch64_features(0xdfff_ffff, 0, None);
+        assert!(!value.test(Feature::dpb2 as u32));
+    }
+}
@@ -3,6 +3,23 @@
 use super::auxvec;
 use crate::detect::{bit, cache, Feature};
 
+// The values are part of the platform-specific [asm/hwcap.h][hwcap]
+//
+// [hwcap]: https://github.com/torvalds/linux/blob/master/arch/arm/include/uapi/asm/hwcap.h
+const HWCAP_NEON: u32 = 12;
+const HWCAP2_AES: u32 = 0;
+const HWCAP2_PMULL: u32 = 1;
+const HWCAP2_SHA1: u32 = 2;
+const HWCAP2_SHA2: u32 = 3;
+const HWCAP2_CRC32: u32 = 4;
+
+/// The `AT_HWCAP` bits that map directly to a feature.
+const HWCAP_FEATURES: &[(u32, Feature)] = &[(HWCAP_NEON, Feature::neon)];
+
+/// The `AT_HWCAP2` bits that map directly to a feature.
+const HWCAP2_FEATURES: &[(u32, Feature)] =
+    &[(HWCAP2_PMULL, Feature::pmull), (HWCAP2_CRC32, Feature::crc)];
+
 /// Try to read the features from the auxiliary vector, and if that fails, try
 /// to read them from /proc/cpuinfo.
 pub(crate) fn detect_features() -> cache::Initializer {
@@ -13,26 +30,24 @@ pub(crate) fn detect_features() -> cache::Initializer {
         }
     };
 
-    // The values are part of the platform-specific [asm/hwcap.h][hwcap]
-    //
-    // [hwcap]: https://github.com/torvalds/linux/blob/master/arch/arm/include/uapi/asm/hwcap.h
     if let Ok(auxv) = auxvec::auxv() {
-        enable_feature(&mut value, Feature::neon, bit::test(auxv.hwcap, 12));
-        enable_feature(&mut value, Feature::pmull, bit::test(auxv.hwcap2, 1));
-        enable_feature(&mut value, Feature::crc, bit::test(auxv.hwcap2, 4));
+        cache::set_detection_source(cache::DetectionSource::Hwcap);
+        bit::enable_features(&mut value, auxv.hwcap, HWCAP_FEATURES);
+        bit::enable_features(&mut value, auxv.hwcap2, HWCAP2_FEATURES);
         enable_feature(
             &mut value,
             Feature::crypto,
-            bit::test(auxv.hwcap2, 0)
-                && bit::test(auxv.hwcap2, 1)
-                && bit::test(auxv.hwcap2, 2)
-                && bit::test(auxv.hwcap2, 3),
+            bit::test(auxv.hwcap2, HWCAP2_AES)
+                && bit::test(auxv.hwcap2, HWCAP2_PMULL)
+                && bit::test(auxv.hwcap2, HWCAP2_SHA1)
+                && bit::test(auxv.hwcap2, HWCAP2_SHA2),
         );
         return value;
     }
 
     #[cfg(feature = "std_detect_file_io")]
     if let Ok(c) = super::cpuinfo::CpuInfo::new() {
+        cache::set_detection_source(cache::DetectionSource::CpuInfo);
         enable_feature(
             &mut value,
             Feature::neon,
@@ -1,12 +1,15 @@
 //! Parses ELF auxiliary vectors.
 #![cfg_attr(not(target_arch = "aarch64"), allow(dead_code))]
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 pub(crate) const AT_NULL: usize = 0;
 
 /// Key to access the CPU Hardware capabilities bitfield.
 pub(crate) const AT_HWCAP: usize = 16;
 /// Key to access the CPU Hardware capabilities 2 bitfield.
 #[cfg(any(
+    target_arch = "aarch64",
     target_arch = "arm",
     target_arch = "powerpc",
     target_arch = "powerpc64"
@@ -21,6 +24,7 @@ pub(crate) const AT_HWCAP2: usize = 26;
 pub(crate) struct AuxVec {
     pub hwcap: usize,
     #[cfg(any(
+        target_arch = "aarch64",
         target_arch = "arm",
         target_arch = "powerpc",
         target_arch = "powerpc64"
@@ -45,6 +49,10 @@ pub(crate) struct AuxVec {
 /// linked to the binary - if that is not the case the behavior is undefined.
 /// - Otherwise, if the `std_detect_file_io` cargo feature is enabled, it will
 ///   try to read `/proc/self/auxv`.
+/// - Otherwise, if the `std_detect_stack_auxv` cargo feature is enabled, it
+///   will read the auxiliary vector that the kernel passed on the initial
+///   stack, whose location is saved by a constructor at program start-up
+///   (with musl only in static binaries).
 /// - If that fails, this function returns an error.
 ///
 /// Note that run-time feature detection is not invoked for features that can
@@ -61,55 +69,16 @@ pub(crate) fn auxv() -> Result<AuxVec, ()> {
     #[cfg(feature = "std_detect_dlsym_getauxval")]
     {
         // Try to call a dynamically-linked getauxval function.
-        if let Ok(hwcap) = getauxval(AT_HWCAP) {
-            // Targets with only AT_HWCAP:
-            #[cfg(any(target_arch = "aarch64", target_arch = "mips", target_arch = "mips64"))]
-            {
-                if hwcap != 0 {
-                    return Ok(AuxVec { hwcap });
-                }
-            }
-
-            // Targets with AT_HWCAP and AT_HWCAP2:
-            #[cfg(any(
-                target_arch = "arm",
-                target_arch = "powerpc",
-                target_arch = "powerpc64"
-            ))]
-            {
-                if let Ok(hwcap2) = getauxval(AT_HWCAP2) {
-                    if hwcap != 0 && hwcap2 != 0 {
-                        return Ok(AuxVec { hwcap, hwcap2 });
-                    }
-                }
-            }
-            drop(hwcap);
+        if let Ok(auxv) = auxv_from_getauxval(|key| cached_getauxval(key, getauxval)) {
+            return Ok(auxv);
         }
     }
 
     #[cfg(not(feature = "std_detect_dlsym_getauxval"))]
     {
-        let hwcap = unsafe { libc::getauxval(AT_HWCAP as libc::c_ulong) as usize };
-
-        // Targets with only AT_HWCAP:
-        #[cfg(any(target_arch = "aarch64", target_arch = "mips", target_arch = "mips64"))]
-        {
-            if hwcap != 0 {
-                return Ok(AuxVec { hwcap });
-            }
-        }
-
-        // Targets with AT_HWCAP and AT_HWCAP2:
-        #[cfg(any(
-            target_arch = "arm",
-            target_arch = "powerpc",
-            target_arch = "powerpc64"
-        ))]
-        {
-            let hwcap2 = unsafe { libc::getauxval(AT_HWCAP2 as libc::c_ulong) as usize };
-            if hwcap != 0 && hwcap2 != 0 {
-                return Ok(AuxVec { hwcap, hwcap2 });
-            }
+        let getauxval = |key| Ok(unsafe { libc::getauxval(key as libc::c_ulong) as usize });
+        if let Ok(auxv) = auxv_from_getauxval(|key| cached_getauxval(key, getauxval)) {
+            return Ok(auxv);
         }
     }
 
@@ -117,12 +86,142 @@ pub(crate) fn auxv() -> Result<AuxVec, ()> {
     {
         // If calling getauxval fails, try to read the auxiliary vector from
         // its file:
-        auxv_from_file("/proc/self/auxv")
+        if let Ok(auxv) = auxv_from_file("/proc/self/auxv") {
+            return Ok(auxv);
+        }
+    }
+
+    #[cfg(feature = "std_detect_stack_auxv")]
+    {
+        // As a last resort, e.g. in static binaries without getauxval that
+        // run in sandboxes without /proc, read the auxiliary vector from the
+        // initial stack:
+        if let Ok(auxv) = stack::auxv_from_stack() {
+            return Ok(auxv);
+        }
     }
-    #[cfg(not(feature = "std_detect_file_io"))]
+
+    Err(())
+}
+
+/// Reads the `AT_HWCAP*` keys of the auxiliary vector with `getauxval`. If
+/// the keys that the target requires are missing, this function returns
+/// `Err`.
+///
+/// `getauxval` is called at most once for `AT_HWCAP` and once for
+/// `AT_HWCAP2`, which the `getauxval_calls_are_bounded` test enforces.
+fn auxv_from_getauxval(
+    mut getauxval: impl FnMut(usize) -> Result<usize, ()>,
+) -> Result<AuxVec, ()> {
+    let hwcap = getauxval(AT_HWCAP)?;
+
+    // Targets with only AT_HWCAP:
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
     {
-        Err(())
+        if hwcap != 0 {
+            return Ok(AuxVec { hwcap });
+        }
     }
+
+    // Targets where AT_HWCAP2 is only provided by newer kernels:
+    #[cfg(target_arch = "aarch64")]
+    {
+        if hwcap != 0 {
+            let hwcap2 = getauxval(AT_HWCAP2).unwrap_or(0);
+            return Ok(AuxVec { hwcap, hwcap2 });
+        }
+    }
+
+    // Targets with AT_HWCAP and AT_HWCAP2:
+    #[cfg(any(
+        target_arch = "arm",
+        target_arch = "powerpc",
+        target_arch = "powerpc64"
+    ))]
+    {
+        if hwcap != 0 {
+            let hwcap2 = getauxval(AT_HWCAP2)?;
+            if hwcap2 != 0 {
+                return Ok(AuxVec { hwcap, hwcap2 });
+            }
+        }
+    }
+    let _ = hwcap;
+    Err(())
+}
+
+/// A `getauxval` result that is computed at most once.
+///
+/// The auxiliary vector does not change during the lifetime of the process,
+/// so `auxv` can be called by several detection functions, or again after a
+/// failed detection, without calling `getauxval` again.
+struct CachedAuxval {
+    /// `UNKNOWN`, `FOUND` if `value` holds the result, or `NOT_FOUND`.
+    state: AtomicUsize,
+    value: AtomicUsize,
+}
+
+impl CachedAuxval {
+    const UNKNOWN: usize = 0;
+    const FOUND: usize = 1;
+    const NOT_FOUND: usize = 2;
+
+    const fn new() -> Self {
+        CachedAuxval {
+            state: AtomicUsize::new(Self::UNKNOWN),
+            value: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the cached result, or computes it with `f` if there is none.
+    ///
+    /// Threads that race to compute the result all call `f`, which is fine
+    /// because they compute the same result.
+    fn get(&self, f: impl FnOnce() -> Result<usize, ()>) -> Result<usize, ()> {
+        match self.state.load(Ordering::Acquire) {
+            Self::FOUND => return Ok(self.value.load(Ordering::Relaxed)),
+            Self::NOT_FOUND => return Err(()),
+            _ => (),
+        }
+        let result = f();
+        match result {
+            Ok(value) => {
+                self.value.store(value, Ordering::Relaxed);
+                self.state.store(Self::FOUND, Ordering::Release);
+            }
+            Err(()) => self.state.store(Self::NOT_FOUND, Ordering::Release),
+        }
+        result
+    }
+}
+
+/// Reads the `key` from the auxiliary vector with `getauxval`, which is only
+/// called the first time each of the `AT_HWCAP*` keys is read.
+fn cached_getauxval(
+    key: usize,
+    getauxval: impl FnOnce(usize) -> Result<usize, ()>,
+) -> Result<usize, ()> {
+    static HWCAP: CachedAuxval = CachedAuxval::new();
+    #[cfg(any(
+        target_arch = "aarch64",
+        target_arch = "arm",
+        target_arch = "powerpc",
+        target_arch = "powerpc64"
+    ))]
+    static HWCAP2: CachedAuxval = CachedAuxval::new();
+
+    let cache = match key {
+        AT_HWCAP => &HWCAP,
+        #[cfg(any(
+            target_arch = "aarch64",
+            target_arch = "arm",
+            target_arch = "powerpc",
+            target_arch = "powerpc64"
+        ))]
+        AT_HWCAP2 => &HWCAP2,
+        _ => return getauxval(key),
+    };
+    cache.get(|| getauxval(key))
 }
 
 /// Tries to read the `key` from the auxiliary vector by calling the
@@ -155,7 +254,7 @@ fn auxv_from_file(file: &str) -> Result<AuxVec, ()> {
     // `AT_EXECFN = 31` to `AT_NULL = 0`. That is, a buffer of
     // 2*32 `usize` elements is enough to read the whole vector.
     let mut buf = [0_usize; 64];
-    let len = core::mem::size_of_val(&buf).max(file.len());
+    let len = core::mem::size_of_val(&buf).min(file.len());
     unsafe {
         core::ptr::copy_nonoverlapping(file.as_ptr(), buf.as_mut_ptr() as *mut u8, len);
     }
@@ -167,16 +266,41 @@ fn auxv_from_file(file: &str) -> Result<AuxVec, ()> {
 /// function returns `Err`.
 #[cfg(feature = "std_detect_file_io")]
 fn auxv_from_buf(buf: &[usize; 64]) -> Result<AuxVec, ()> {
+    auxv_from_pairs(buf.chunks(2).map(|el| (el[0], el[1])))
+}
+
+/// Tries to interpret the `(key, value)` pairs as an auxiliary vector, up to
+/// the first `AT_NULL` key. If that fails, this function returns `Err`.
+#[cfg(any(feature = "std_detect_file_io", feature = "std_detect_stack_auxv"))]
+fn auxv_from_pairs(mut pairs: impl Iterator<Item = (usize, usize)>) -> Result<AuxVec, ()> {
     // Targets with only AT_HWCAP:
-    #[cfg(any(target_arch = "aarch64", target_arch = "mips", target_arch = "mips64"))]
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+    {
+        for (key, value) in pairs.by_ref() {
+            match key {
+                AT_NULL => break,
+                AT_HWCAP => return Ok(AuxVec { hwcap: value }),
+                _ => (),
+            }
+        }
+    }
+    // Targets where AT_HWCAP2 is only provided by newer kernels:
+    #[cfg(target_arch = "aarch64")]
     {
-        for el in buf.chunks(2) {
-            match el[0] {
+        let mut hwcap = None;
+        let mut hwcap2 = 0;
+        for (key, value) in pairs.by_ref() {
+            match key {
                 AT_NULL => break,
-                AT_HWCAP => return Ok(AuxVec { hwcap: el[1] }),
+                AT_HWCAP => hwcap = Some(value),
+                AT_HWCAP2 => hwcap2 = value,
                 _ => (),
             }
         }
+
+        if let Some(hwcap) = hwcap {
+            return Ok(AuxVec { hwcap, hwcap2 });
+        }
     }
     // Targets with AT_HWCAP and AT_HWCAP2:
     #[cfg(any(
@@ -187,11 +311,11 @@ fn auxv_from_buf(buf: &[usize; 64]) -> Result<AuxVec, ()> {
     {
         let mut hwcap = None;
         let mut hwcap2 = None;
-        for el in buf.chunks(2) {
-            match el[0] {
+        for (key, value) in pairs.by_ref() {
+            match key {
                 AT_NULL => break,
-                AT_HWCAP => hwcap = Some(el[1]),
-                AT_HWCAP2 => hwcap2 = Some(el[1]),
+                AT_HWCAP => hwcap = Some(value),
+                AT_HWCAP2 => hwcap2 = Some(value),
                 _ => (),
             }
         }
@@ -200,10 +324,213 @@ fn auxv_from_buf(buf: &[usize; 64]) -> Result<AuxVec, ()> {
             return Ok(AuxVec { hwcap, hwcap2 });
         }
     }
-    drop(buf);
+    drop(pairs);
     Err(())
 }
 
+/// Reads the auxiliary vector from the initial stack of the process.
+///
+/// At program start-up the kernel places `argc`, the `argv` and `envp`
+/// arrays, each terminated by a null pointer, and then the auxiliary vector
+/// on the stack. glibc passes the initial `argc`, `argv`, and `envp` to the
+/// constructors in `.init_array`, so the one below finds the auxiliary vector
+/// after the terminator of `envp` and saves its address. glibc keeps the
+/// initial stack alive for the lifetime of the process.
+///
+/// When std_detect is part of a library that is loaded with `dlopen`, glibc
+/// passes the current `environ` as `envp`, which need not be the array on the
+/// initial stack anymore; the address is only saved when `envp` directly
+/// follows the terminator of `argv`, as it does on the initial stack.
+///
+/// musl calls constructors without arguments. In static binaries, which
+/// cannot `dlopen` libraries, `__init_libc` has set `environ` to the initial
+/// `envp` before the constructors run, so the constructor finds the auxiliary
+/// vector after `environ` instead. In dynamically linked musl programs
+/// `environ` may have been modified before a library is loaded, so there the
+/// constructor is not registered and the auxiliary vector is never read from
+/// the stack.
+#[cfg(feature = "std_detect_stack_auxv")]
+mod stack {
+    use super::AuxVec;
+    use core::sync::atomic::{AtomicPtr, Ordering};
+
+    /// The address of the auxiliary vector on the initial stack, or null if
+    /// it has not been found.
+    static AUXV: AtomicPtr<usize> = AtomicPtr::new(core::ptr::null_mut());
+
+    #[cfg(not(target_env = "musl"))]
+    #[used]
+    #[link_section = ".init_array"]
+    static SAVE_AUXV: extern "C" fn(
+        libc::c_int,
+        *const *const libc::c_char,
+        *const *const libc::c_char,
+    ) = save_auxv;
+
+    #[cfg(all(target_env = "musl", target_feature = "crt-static"))]
+    #[used]
+    #[link_section = ".init_array"]
+    static SAVE_AUXV: extern "C" fn() = save_auxv;
+
+    #[cfg(all(target_env = "musl", target_feature = "crt-static"))]
+    extern "C" fn save_auxv() {
+        extern "C" {
+            static environ: *const *const libc::c_char;
+        }
+        // `environ` is still the initial `envp` at this point, see above.
+        let auxv = unsafe { auxv_after_envp(environ) };
+        AUXV.store(auxv as *mut usize, Ordering::Relaxed);
+    }
+
+    #[cfg(not(target_env = "musl"))]
+    extern "C" fn save_auxv(
+        argc: libc::c_int,
+        argv: *const *const libc::c_char,
+        envp: *const *const libc::c_char,
+    ) {
+        let auxv = unsafe { auxv_after_args(argc, argv, envp) };
+        AUXV.store(auxv as *mut usize, Ordering::Relaxed);
+    }
+
+    /// Returns the address of the auxiliary vector that follows the `argv`
+    /// and `envp` arrays on the initial stack, or null if `envp` does not
+    /// directly follow the terminator of `argv`.
+    ///
+    /// # Safety
+    ///
+    /// `argv` must be null or point to at least `argc + 1` pointers, and
+    /// `envp` must be null or point to a null-terminated array of pointers.
+    #[cfg(any(test, not(target_env = "musl")))]
+    pub(super) unsafe fn auxv_after_args(
+        argc: libc::c_int,
+        argv: *const *const libc::c_char,
+        envp: *const *const libc::c_char,
+    ) -> *const usize {
+        if argc < 0 || argv.is_null() {
+            return core::ptr::null();
+        }
+        let argv_end = argv.add(argc as usize);
+        if !(*argv_end).is_null() || argv_end.add(1) != envp {
+            return core::ptr::null();
+        }
+        auxv_after_envp(envp)
+    }
+
+    /// Returns the address of the auxiliary vector that follows the
+    /// null-terminated `envp` array, or null if `envp` is null.
+    ///
+    /// # Safety
+    ///
+    /// `envp` must be null or point to a null-terminated array of pointers.
+    #[cfg(any(test, not(target_env = "musl"), target_feature = "crt-static"))]
+    pub(super) unsafe fn auxv_after_envp(mut envp: *const *const libc::c_char) -> *const usize {
+        if envp.is_null() {
+            return core::ptr::null();
+        }
+        while !(*envp).is_null() {
+            envp = envp.add(1);
+        }
+        envp.add(1) as *const usize
+    }
+
+    /// Reads the `(key, value)` pairs of the auxiliary vector at `auxv`.
+    ///
+    /// # Safety
+    ///
+    /// `auxv` must point to an auxiliary vector that is terminated by an
+    /// `AT_NULL` key.
+    pub(super) unsafe fn auxv_from_ptr(auxv: *const usize) -> Result<AuxVec, ()> {
+        let mut entry = auxv;
+        super::auxv_from_pairs(core::iter::from_fn(|| {
+            // `auxv_from_pairs` stops at the `AT_NULL` key, so the entries
+            // after it are never read.
+            let pair = (*entry, *entry.add(1));
+            entry = entry.add(2);
+            Some(pair)
+        }))
+    }
+
+    /// Reads the auxiliary vector whose address was saved at start-up.
+    pub(super) fn auxv_from_stack() -> Result<AuxVec, ()> {
+        let auxv = AUXV.load(Ordering::Relaxed);
+        if auxv.is_null() {
+            return Err(());
+        }
+        // Safe because `save_auxv` found it after the initial `envp`.
+        unsafe { auxv_from_ptr(auxv) }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::{AT_HWCAP, AT_NULL};
+        use super::*;
+
+        #[test]
+        fn stack_layout() {
+            const AT_PAGESZ: usize = 6;
+            const AT_HWCAP2: usize = 26;
+            let var = b"A=1\0".as_ptr() as usize;
+            // `envp` with two variables, followed by the auxiliary vector:
+            let stack = [
+                var, var, 0, AT_PAGESZ, 4096, AT_HWCAP, 0x1234, AT_HWCAP2, 0x5678, AT_NULL, 0,
+            ];
+            let auxv = unsafe { auxv_after_envp(stack.as_ptr() as *const *const libc::c_char) };
+            assert_eq!(auxv, stack[3..].as_ptr());
+            let v = unsafe { auxv_from_ptr(auxv) }.unwrap();
+            assert_eq!(v.hwcap, 0x1234);
+            #[cfg(any(
+                target_arch = "aarch64",
+                target_arch = "arm",
+                target_arch = "powerpc",
+                target_arch = "powerpc64"
+            ))]
+            assert_eq!(v.hwcap2, 0x5678);
+
+            // An empty `envp` and auxiliary vector:
+            let stack = [0, AT_NULL, 0];
+            let auxv = unsafe { auxv_after_envp(stack.as_ptr() as *const *const libc::c_char) };
+            assert_eq!(auxv, stack[1..].as_ptr());
+            assert!(unsafe { auxv_from_ptr(auxv) }.is_err());
+
+            assert!(unsafe { auxv_after_envp(core::ptr::null()) }.is_null());
+        }
+
+        #[test]
+        fn initial_stack_args() {
+            let arg = b"prog\0".as_ptr() as usize;
+            // `argv` with one argument, `envp` with one variable, and the
+            // auxiliary vector:
+            let stack = [arg, 0, arg, 0, AT_HWCAP, 0x1234, AT_NULL, 0];
+            let argv = stack.as_ptr() as *const *const libc::c_char;
+            let envp = stack[2..].as_ptr() as *const *const libc::c_char;
+            let auxv = unsafe { auxv_after_args(1, argv, envp) };
+            assert_eq!(auxv, stack[4..].as_ptr());
+            assert_eq!(unsafe { auxv_from_ptr(auxv) }.unwrap().hwcap, 0x1234);
+
+            // An `envp` that is not on the initial stack, e.g. the `environ`
+            // of a program that modified its environment before `dlopen`:
+            let environ = [arg, 0];
+            let moved = environ.as_ptr() as *const *const libc::c_char;
+            assert!(unsafe { auxv_after_args(1, argv, moved) }.is_null());
+            // A wrong `argc`:
+            assert!(unsafe { auxv_after_args(0, argv, envp) }.is_null());
+            assert!(unsafe { auxv_after_args(-1, argv, envp) }.is_null());
+            assert!(unsafe { auxv_after_args(1, core::ptr::null(), envp) }.is_null());
+        }
+
+        #[test]
+        #[cfg(all(
+            feature = "std_detect_file_io",
+            any(not(target_env = "musl"), target_feature = "crt-static")
+        ))]
+        fn saved_at_start_up() {
+            let v = auxv_from_stack().unwrap();
+            let file = super::super::auxv_from_file("/proc/self/auxv").unwrap();
+            assert_eq!(v.hwcap, file.hwcap);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate auxv as auxv_crate;
@@ -253,6 +580,7 @@ mod tests {
 
         // Targets with AT_HWCAP and AT_HWCAP2:
         #[cfg(any(
+            target_arch = "aarch64",
             target_arch = "arm",
             target_arch = "powerpc",
             target_arch = "powerpc64"
@@ -309,6 +637,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn getauxval_calls_are_bounded() {
+        // The most `getauxval` calls that reading the auxiliary vector may
+        // issue: `AT_HWCAP` and `AT_HWCAP2`. Reading another key requires
+        // raising this bound.
+        const MAX_GETAUXVAL_CALLS: usize = 2;
+
+        let results = [Err(()), Ok(0), Ok(0x1234)];
+        for &hwcap in &results {
+            for &hwcap2 in &results {
+                let mut keys = std::vec::Vec::new();
+                let _ = auxv_from_getauxval(|key| {
+                    keys.push(key);
+                    match key {
+                        AT_HWCAP => hwcap,
+                        _ => hwcap2,
+                    }
+                });
+                assert!(keys.len() <= MAX_GETAUXVAL_CALLS, "{:?}", keys);
+                for (i, key) in keys.iter().enumerate() {
+                    assert!(!keys[..i].contains(key), "{} read twice", key);
+                }
+            }
+        }
+
+        // The results are cached, so reading the auxiliary vector again does
+        // not call `getauxval` at all, see `cached_auxval`.
+    }
+
+    #[test]
+    fn cached_auxval() {
+        let cache = CachedAuxval::new();
+        let mut calls = 0;
+        let mut getauxval = || {
+            calls += 1;
+            Ok(0x1234)
+        };
+        assert_eq!(cache.get(&mut getauxval), Ok(0x1234));
+        assert_eq!(cache.get(&mut getauxval), Ok(0x1234));
+        assert_eq!(calls, 1);
+
+        // Failures are cached as well:
+        let cache = CachedAuxval::new();
+        let mut calls = 0;
+        let mut getauxval = || {
+            calls += 1;
+            Err(())
+        };
+        assert_eq!(cache.get(&mut getauxval), Err(()));
+        assert_eq!(cache.get(&mut getauxval), Err(()));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "std_detect_file_io")]
+    fn auxv_from_unreadable_file() {
+        // E.g. if a sandbox blocks access to `/proc` or only allows reading
+        // parts of it:
+        assert!(auxv_from_file("/proc/self/nonexistent").is_err());
+        assert!(auxv_from_file("/dev/null").is_err());
+    }
+
     #[test]
     #[cfg(feature = "std_detect_file_io")]
     fn auxv_dump_procfs() {
@@ -335,6 +725,7 @@ mod tests {
 
         // Targets with AT_HWCAP and AT_HWCAP2:
         #[cfg(any(
+            target_arch = "aarch64",
             target_arch = "arm",
             target_arch = "powerpc",
             target_arch = "powerpc64"
@@ -0,0 +1,30 @@
+//! Run-time detection of the state of Control-flow Enforcement Technology
+//! (CET) for the current thread on Linux.
+
+/// The `arch_prctl` code that reports the shadow stack features that are
+/// enabled for the current thread, see `arch/x86/include/uapi/asm/prctl.h`.
+const ARCH_SHSTK_STATUS: libc::c_int = 0x5005;
+/// The shadow stack feature of `ARCH_SHSTK_STATUS`.
+const ARCH_SHSTK_SHSTK: u64 = 1 << 0;
+
+/// Returns `true` if the CET shadow stack is enabled for the current thread.
+///
+/// This is distinct from CPU support for shadow stacks
+/// (`CPUID.(EAX=7,ECX=0):ECX.CET_SS[bit 7]`): the kernel only enables them for
+/// processes whose binaries are marked as compatible. This queries
+/// `arch_prctl(ARCH_SHSTK_STATUS)`, which was added in Linux 6.6 in place of
+/// the `ARCH_CET_STATUS` of earlier out-of-tree patches. If the syscall is
+/// unavailable, `false` is returned.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __cet_shadow_stack_active() -> bool {
+    let mut features: u64 = 0;
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_arch_prctl,
+            ARCH_SHSTK_STATUS,
+            &mut features as *mut u64,
+        )
+    };
+    ret == 0 && features & ARCH_SHSTK_SHSTK != 0
+}
@@ -0,0 +1,61 @@
+//! Run-time detection of CPUID faulting for the current thread on Linux.
+
+/// The `arch_prctl` code that reports whether the `cpuid` instruction can be
+/// executed by the current thread, see `arch/x86/include/uapi/asm/prctl.h`.
+const ARCH_GET_CPUID: libc::c_int = 0x1011;
+
+/// Returns `true` if executing `cpuid` raises `SIGSEGV` in the current thread.
+///
+/// The kernel traps `cpuid` on processors that support CPUID faulting if the
+/// thread disabled it with `arch_prctl(ARCH_SET_CPUID, 0)`, which is inherited
+/// by child processes until `execve`. `arch_prctl(ARCH_GET_CPUID)` was added
+/// in Linux 4.12; if it is unavailable or blocked (e.g. by a seccomp filter
+/// that returns an error), `cpuid` is assumed to be usable.
+pub(crate) fn cpuid_faulting() -> bool {
+    let ret = unsafe { libc::syscall(libc::SYS_arch_prctl, ARCH_GET_CPUID, 0) };
+    cpuid_faulting_from(ret)
+}
+
+/// Interprets the return value of `arch_prctl(ARCH_GET_CPUID)`: `0` if
+/// `cpuid` faults, `1` if it does not, and `-1` on error.
+fn cpuid_faulting_from(ret: libc::c_long) -> bool {
+    ret == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::{cache, os, Feature};
+
+    const ARCH_SET_CPUID: libc::c_int = 0x1012;
+
+    #[test]
+    fn arch_get_cpuid() {
+        assert!(cpuid_faulting_from(0));
+        assert!(!cpuid_faulting_from(1));
+        // E.g. `ENOSYS` on Linux < 4.12, or `EPERM` from a seccomp filter:
+        assert!(!cpuid_faulting_from(-1));
+    }
+
+    #[test]
+    fn detect_features_with_cpuid_faulting() {
+        // This only affects the thread of this test.
+        let ret = unsafe { libc::syscall(libc::SYS_arch_prctl, ARCH_SET_CPUID, 0) };
+        if ret != 0 {
+            // The processor does not support CPUID faulting (`ENODEV`), which
+            // is common in virtual machines.
+            return;
+        }
+        let faulting = cpuid_faulting();
+        let value = os::detect_features();
+        let signature = os::__cpu_signature();
+        let ret = unsafe { libc::syscall(libc::SYS_arch_prctl, ARCH_SET_CPUID, 1) };
+        assert_eq!(ret, 0);
+
+        assert!(faulting);
+        assert_eq!(value, cache::Initializer::default());
+        assert!(signature.is_none());
+        assert!(!cpuid_faulting());
+        assert!(os::detect_features().test(Feature::sse2 as u32));
+    }
+}
@@ -33,7 +33,7 @@ impl CpuInfo {
     }
 
     #[cfg(test)]
-    fn from_str(other: &str) -> Result<Self, ()> {
+    pub(crate) fn from_str(other: &str) -> Result<Self, ()> {
         Ok(Self {
             raw: String::from(other),
         })
@@ -80,6 +80,14 @@ impl<'a> CpuInfoField<'a> {
             }
         }
     }
+    /// Returns `N` if the field starts with the name of an IBM `POWERN`
+    /// processor, like the `cpu` field of `/proc/cpuinfo` on PowerPC (e.g.
+    /// `POWER8E (raw), altivec supported`).
+    pub(crate) fn power_generation(&self) -> Option<u32> {
+        let model = self.0?.strip_prefix("POWER")?;
+        let digits = model.bytes().take_while(u8::is_ascii_digit).count();
+        model[..digits].parse().ok()
+    }
 }
 
 #[cfg(test)]
@@ -243,6 +251,7 @@ machine         : CHRP IBM pSeries (emulated by qemu)";
         assert_eq!(cpuinfo.field("cpu"), "POWER8E (raw), altivec supported");
 
         assert!(cpuinfo.field("cpu").has("altivec"));
+        assert_eq!(cpuinfo.field("cpu").power_generation(), Some(8));
     }
 
     const POWER5P: &str = r"processor       : 0
@@ -295,5 +304,20 @@ machine         : CHRP IBM,9133-55A";
         assert_eq!(cpuinfo.field("cpu"), "POWER5+ (gs)");
 
         assert!(!cpuinfo.field("cpu").has("altivec"));
+        assert_eq!(cpuinfo.field("cpu").power_generation(), Some(5));
+    }
+
+    #[test]
+    fn power_generation() {
+        let generation = |cpu| CpuInfoField::new(Some(cpu)).power_generation();
+        assert_eq!(generation("POWER9 (raw), altivec supported"), Some(9));
+        assert_eq!(
+            generation("POWER10 (architected), altivec supported"),
+            Some(10)
+        );
+        assert_eq!(generation("POWER7+ (raw), altivec supported"), Some(7));
+        assert_eq!(generation("PPC970MP, altivec supported"), None);
+        assert_eq!(generation("POWER"), None);
+        assert_eq!(CpuInfoField::new(None).power_generation(), None);
     }
 }
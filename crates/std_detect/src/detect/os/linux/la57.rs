@@ -0,0 +1,92 @@
+//! Run-time detection of whether Linux uses 5-level paging.
+
+/// Returns whether the kernel uses 5-level paging, or `None` if this cannot
+/// be determined.
+///
+/// The kernel clears the `la57` flag of `/proc/cpuinfo` if it does not enable
+/// 5-level paging, e.g., because it was built without
+/// `CONFIG_X86_5LEVEL` or booted with `no5lvl`, even if the processor
+/// supports it.
+pub(crate) fn la57_enabled() -> Option<bool> {
+    // The flags of the first processor are well within the first few KiB.
+    let mut buf = [0_u8; 8192];
+    let len = unsafe {
+        let file = libc::open(
+            b"/proc/cpuinfo\0".as_ptr() as *const libc::c_char,
+            libc::O_RDONLY,
+        );
+        if file == -1 {
+            return None;
+        }
+        let mut len = 0;
+        while len < buf.len() {
+            match libc::read(
+                file,
+                buf[len..].as_mut_ptr() as *mut libc::c_void,
+                buf.len() - len,
+            ) {
+                -1 => {
+                    libc::close(file);
+                    return None;
+                }
+                0 => break,
+                n => len += n as usize,
+            }
+        }
+        libc::close(file);
+        len
+    };
+    la57_from_cpuinfo(&buf[..len])
+}
+
+/// Looks for the `la57` flag in the first complete `flags` line of
+/// `cpuinfo`, or returns `None` if there is none.
+fn la57_from_cpuinfo(cpuinfo: &[u8]) -> Option<bool> {
+    let mut lines = cpuinfo.split(|&b| b == b'\n');
+    // The last line may have been truncated:
+    let _ = lines.next_back();
+    for line in lines {
+        let mut parts = line.splitn(2, |&b| b == b':');
+        let key = parts.next()?;
+        if key.split(|&b| b == b'\t').next() != Some(&b"flags"[..]) {
+            continue;
+        }
+        let flags = parts.next()?;
+        return Some(flags.split(|&b| b == b' ').any(|flag| flag == b"la57"));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpuinfo_flags() {
+        let cpuinfo = b"processor\t: 0\n\
+            model name\t: Intel(R) Xeon(R) Platinum 8380 CPU @ 2.30GHz\n\
+            flags\t\t: fpu vme la57 rdpid\n\
+            bugs\t\t: spectre_v1\n";
+        assert_eq!(la57_from_cpuinfo(cpuinfo), Some(true));
+        // Booted with `no5lvl`:
+        let cpuinfo = b"processor\t: 0\nflags\t\t: fpu vme rdpid\n";
+        assert_eq!(la57_from_cpuinfo(cpuinfo), Some(false));
+        // `la57` is a whole flag:
+        let cpuinfo = b"flags\t\t: fpu la57x\n";
+        assert_eq!(la57_from_cpuinfo(cpuinfo), Some(false));
+        // A truncated line, or none at all:
+        assert_eq!(la57_from_cpuinfo(b"flags\t\t: fpu la5"), None);
+        assert_eq!(la57_from_cpuinfo(b"processor\t: 0\n"), None);
+        assert_eq!(la57_from_cpuinfo(b""), None);
+        // `vmx flags` on newer kernels is not the `flags` line:
+        let cpuinfo = b"vmx flags\t: ept\nflags\t\t: la57\n";
+        assert_eq!(la57_from_cpuinfo(cpuinfo), Some(true));
+    }
+
+    #[test]
+    fn host() {
+        if let Some(enabled) = la57_enabled() {
+            assert!(!enabled || crate::detect::os::__la57_active());
+        }
+    }
+}
@@ -3,21 +3,22 @@
 use super::auxvec;
 use crate::detect::{bit, cache, Feature};
 
+// The values are part of the platform-specific [asm/hwcap.h][hwcap]
+//
+// [hwcap]: https://github.com/torvalds/linux/blob/master/arch/mips/include/uapi/asm/hwcap.h
+const HWCAP_MIPS_MSA: u32 = 1;
+
+/// The `AT_HWCAP` bits that map directly to a feature.
+const HWCAP_FEATURES: &[(u32, Feature)] = &[(HWCAP_MIPS_MSA, Feature::msa)];
+
 /// Try to read the features from the auxiliary vector, and if that fails, try
 /// to read them from `/proc/cpuinfo`.
 pub(crate) fn detect_features() -> cache::Initializer {
     let mut value = cache::Initializer::default();
-    let enable_feature = |value: &mut cache::Initializer, f, enable| {
-        if enable {
-            value.set(f as u32);
-        }
-    };
 
-    // The values are part of the platform-specific [asm/hwcap.h][hwcap]
-    //
-    // [hwcap]: https://github.com/torvalds/linux/blob/master/arch/arm64/include/uapi/asm/hwcap.h
     if let Ok(auxv) = auxvec::auxv() {
-        enable_feature(&mut value, Feature::msa, bit::test(auxv.hwcap, 1));
+        cache::set_detection_source(cache::DetectionSource::Hwcap);
+        bit::enable_features(&mut value, auxv.hwcap, HWCAP_FEATURES);
         return value;
     }
     // TODO: fall back via `cpuinfo`.
@@ -1,36 +1,189 @@
 //! Run-time feature detection for PowerPC on Linux.
 
 use super::auxvec;
-use crate::detect::{cache, Feature};
+use crate::detect::{bit, cache, Feature};
+
+// The values are part of the platform-specific [asm/cputable.h][cputable]
+//
+// [cputable]: https://github.com/torvalds/linux/blob/master/arch/powerpc/include/uapi/asm/cputable.h
+//
+// note: cputable.h defines the PowerPC values as the mask to do the test
+// (e.g. `PPC_FEATURE_HAS_ALTIVEC` is `0x10000000`); these are the indices of
+// the bits to test like in ARM and Aarch64.
+const PPC_FEATURE_HAS_ALTIVEC: u32 = 28;
+const PPC_FEATURE_HAS_VSX: u32 = 7;
+const PPC_FEATURE2_ARCH_2_07: u32 = 31;
+const PPC_FEATURE2_ARCH_3_00: u32 = 23;
+const PPC_FEATURE2_ARCH_3_1: u32 = 18;
+const PPC_FEATURE2_HTM: u32 = 30;
+const PPC_FEATURE2_EBB: u32 = 28;
+const PPC_FEATURE2_ISEL: u32 = 27;
+const PPC_FEATURE2_HTM_NOSC: u32 = 24;
+
+/// The `AT_HWCAP` bits that map directly to a feature.
+const HWCAP_FEATURES: &[(u32, Feature)] = &[
+    (PPC_FEATURE_HAS_ALTIVEC, Feature::altivec),
+    (PPC_FEATURE_HAS_VSX, Feature::vsx),
+];
+
+/// The `AT_HWCAP2` bits that map directly to a feature.
+const HWCAP2_FEATURES: &[(u32, Feature)] = &[
+    (PPC_FEATURE2_ARCH_2_07, Feature::power8),
+    (PPC_FEATURE2_ARCH_3_00, Feature::power9),
+    (PPC_FEATURE2_ARCH_3_1, Feature::power10),
+    (PPC_FEATURE2_HTM, Feature::htm),
+    (PPC_FEATURE2_HTM_NOSC, Feature::htm_nosc),
+    (PPC_FEATURE2_EBB, Feature::ebb),
+    (PPC_FEATURE2_ISEL, Feature::isel),
+];
 
 /// Try to read the features from the auxiliary vector, and if that fails, try
 /// to read them from /proc/cpuinfo.
 pub(crate) fn detect_features() -> cache::Initializer {
-    let mut value = cache::Initializer::default();
-    let enable_feature = |value: &mut cache::Initializer, f, enable| {
-        if enable {
-            value.set(f as u32);
-        }
-    };
-
-    // The values are part of the platform-specific [asm/cputable.h][cputable]
-    //
-    // [cputable]: https://github.com/torvalds/linux/blob/master/arch/powerpc/include/uapi/asm/cputable.h
     if let Ok(auxv) = auxvec::auxv() {
-        // note: the PowerPC values are the mask to do the test (instead of the
-        // index of the bit to test like in ARM and Aarch64)
-        enable_feature(&mut value, Feature::altivec, auxv.hwcap & 0x10000000 != 0);
-        enable_feature(&mut value, Feature::vsx, auxv.hwcap & 0x00000080 != 0);
-        enable_feature(&mut value, Feature::power8, auxv.hwcap2 & 0x80000000 != 0);
-        return value;
+        cache::set_detection_source(cache::DetectionSource::Hwcap);
+        return hwcap_features(auxv.hwcap, auxv.hwcap2);
     }
 
-    // PowerPC's /proc/cpuinfo lacks a proper Feature field,
-    // but `altivec` support is indicated in the `cpu` field.
     #[cfg(feature = "std_detect_file_io")]
     if let Ok(c) = super::cpuinfo::CpuInfo::new() {
-        enable_feature(&mut value, Feature::altivec, c.field("cpu").has("altivec"));
-        return value;
+        cache::set_detection_source(cache::DetectionSource::CpuInfo);
+        return cpuinfo_features(&c);
     }
+    cache::Initializer::default()
+}
+
+/// Computes the features from the `AT_HWCAP` and `AT_HWCAP2` bitfields.
+fn hwcap_features(hwcap: usize, hwcap2: usize) -> cache::Initializer {
+    let mut value = cache::Initializer::default();
+    bit::enable_features(&mut value, hwcap, HWCAP_FEATURES);
+    bit::enable_features(&mut value, hwcap2, HWCAP2_FEATURES);
     value
 }
+
+/// Infers the features from /proc/cpuinfo.
+///
+/// PowerPC's /proc/cpuinfo lacks a proper Feature field, but `altivec`
+/// support is indicated in the `cpu` field, which also names the processor.
+/// For IBM POWER processors, the ISA level is inferred from the generation:
+/// POWER7 implements ISA 2.06 (VSX), POWER8 ISA 2.07, POWER9 ISA 3.0, and
+/// POWER10 ISA 3.1.
+#[cfg(feature = "std_detect_file_io")]
+fn cpuinfo_features(c: &super::cpuinfo::CpuInfo) -> cache::Initializer {
+    let mut value = cache::Initializer::default();
+    let cpu = c.field("cpu");
+    let altivec = cpu.has("altivec");
+    if altivec {
+        value.set(Feature::altivec as u32);
+    }
+    // The kernel only reports `altivec supported` if it saves the vector
+    // registers, which VSX extends.
+    let generation = cpu.power_generation().unwrap_or(0);
+    if altivec && generation >= 7 {
+        value.set(Feature::vsx as u32);
+    }
+    if altivec && generation >= 8 {
+        value.set(Feature::power8 as u32);
+    }
+    if altivec && generation >= 9 {
+        value.set(Feature::power9 as u32);
+    }
+    if altivec && generation >= 10 {
+        value.set(Feature::power10 as u32);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std_detect_file_io")]
+    use super::super::cpuinfo::CpuInfo;
+    use super::*;
+
+    #[test]
+    fn hwcap2_htm() {
+        let htm = |hwcap2: usize| {
+            let value = hwcap_features(0, hwcap2);
+            (
+                value.test(Feature::htm as u32),
+                value.test(Feature::htm_nosc as u32),
+            )
+        };
+        assert_eq!(htm(0), (false, false));
+        // PPC_FEATURE2_HTM:
+        assert_eq!(htm(0x4000_0000), (true, false));
+        // PPC_FEATURE2_HTM_NOSC:
+        assert_eq!(htm(0x0100_0000), (false, true));
+        // POWER8 and POWER9: PPC_FEATURE2_ARCH_2_07 | PPC_FEATURE2_HTM |
+        // PPC_FEATURE2_HTM_NOSC.
+        assert_eq!(htm(0x8000_0000 | 0x4000_0000 | 0x0100_0000), (true, true));
+        // The bits in `AT_HWCAP` are unrelated:
+        let value = hwcap_features(0x4000_0000 | 0x0100_0000, 0);
+        assert!(!value.test(Feature::htm as u32));
+    }
+
+    #[test]
+    fn hwcap2_ebb_isel() {
+        let ebb_isel = |hwcap2: usize| {
+            let value = hwcap_features(0, hwcap2);
+            (
+                value.test(Feature::ebb as u32),
+                value.test(Feature::isel as u32),
+            )
+        };
+        assert_eq!(ebb_isel(0), (false, false));
+        // PPC_FEATURE2_EBB:
+        assert_eq!(ebb_isel(0x1000_0000), (true, false));
+        // PPC_FEATURE2_ISEL:
+        assert_eq!(ebb_isel(0x0800_0000), (false, true));
+        // POWER8: PPC_FEATURE2_ARCH_2_07 | PPC_FEATURE2_HTM |
+        // PPC_FEATURE2_DSCR | PPC_FEATURE2_EBB | PPC_FEATURE2_ISEL |
+        // PPC_FEATURE2_TAR | PPC_FEATURE2_VEC_CRYPTO.
+        assert_eq!(ebb_isel(0xfe00_0000), (true, true));
+    }
+
+    #[cfg(feature = "std_detect_file_io")]
+    fn features(cpu: &str) -> [bool; 5] {
+        let c = CpuInfo::from_str(&format!("processor\t: 0\ncpu\t\t: {}\n", cpu)).unwrap();
+        let value = cpuinfo_features(&c);
+        [
+            value.test(Feature::altivec as u32),
+            value.test(Feature::vsx as u32),
+            value.test(Feature::power8 as u32),
+            value.test(Feature::power9 as u32),
+            value.test(Feature::power10 as u32),
+        ]
+    }
+
+    #[test]
+    #[cfg(feature = "std_detect_file_io")]
+    fn cpuinfo() {
+        assert_eq!(features("POWER5+ (gs)"), [false; 5]);
+        assert_eq!(
+            features("POWER6 (raw), altivec supported"),
+            [true, false, false, false, false]
+        );
+        assert_eq!(
+            features("POWER7 (architected), altivec supported"),
+            [true, true, false, false, false]
+        );
+        assert_eq!(
+            features("POWER8E (raw), altivec supported"),
+            [true, true, true, false, false]
+        );
+        assert_eq!(
+            features("POWER9 (raw), altivec supported"),
+            [true, true, true, true, false]
+        );
+        assert_eq!(
+            features("POWER10 (architected), altivec supported"),
+            [true, true, true, true, true]
+        );
+        assert_eq!(
+            features("PPC970MP, altivec supported"),
+            [true, false, false, false, false]
+        );
+        // Without kernel support for the vector registers:
+        assert_eq!(features("POWER9 (raw)"), [false; 5]);
+    }
+}
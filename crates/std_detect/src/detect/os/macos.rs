@@ -0,0 +1,27 @@
+//! Run-time detection of properties of the current process on macOS.
+
+/// Returns `true` if the current process is running translated by Rosetta 2.
+///
+/// This reads the `sysctl.proc_translated` sysctl, which is `1` for
+/// translated processes and `0` for native ones. If the sysctl is absent,
+/// e.g., on macOS versions that predate Rosetta 2, `false` is returned.
+///
+/// Rosetta 2 runs x86_64 processes on aarch64 hardware, so this can only
+/// return `true` in x86_64 binaries, which then report the features of the
+/// translation layer instead of the ones of the hardware.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __is_translated() -> bool {
+    let mut translated: libc::c_int = 0;
+    let mut size = core::mem::size_of::<libc::c_int>();
+    let ret = unsafe {
+        libc::sysctlbyname(
+            b"sysctl.proc_translated\0".as_ptr() as *const libc::c_char,
+            &mut translated as *mut libc::c_int as *mut libc::c_void,
+            &mut size,
+            core::ptr::null_mut(),
+            0,
+        )
+    };
+    ret == 0 && translated == 1
+}
@@ -0,0 +1,310 @@
+//! Run-time feature detection for Aarch64 on macOS.
+//!
+//! The features are reported by the `hw.optional.*` sysctls, see the
+//! "Determining Instruction Set Characteristics" article of the Apple
+//! developer documentation.
+
+use crate::detect::{cache, Feature};
+
+/// Returns `true` if the sysctl `name`, which must be NUL-terminated, exists
+/// and is set to a non-zero value.
+fn sysctl_enabled(name: &[u8]) -> bool {
+    assert_eq!(name.last(), Some(&0));
+    let mut enabled: libc::c_int = 0;
+    let mut size = core::mem::size_of::<libc::c_int>();
+    let ret = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr() as *const libc::c_char,
+            &mut enabled as *mut libc::c_int as *mut libc::c_void,
+            &mut size,
+            core::ptr::null_mut(),
+            0,
+        )
+    };
+    ret == 0 && enabled != 0
+}
+
+/// Try to read the features using sysctlbyname.
+pub(crate) fn detect_features() -> cache::Initializer {
+    cache::set_detection_source(cache::DetectionSource::Sysctl);
+    detect_features_with(sysctl_enabled)
+}
+
+/// Computes the features from the `hw.optional.*` sysctls, which are read
+/// with `has(name)`.
+fn detect_features_with(has: impl Fn(&[u8]) -> bool) -> cache::Initializer {
+    let mut value = cache::Initializer::default();
+    {
+        let mut enable_feature = |f, enable| {
+            if enable {
+                value.set(f as u32);
+            }
+        };
+
+        // The `hw.optional.arm.FEAT_*` names were introduced in macOS 12;
+        // earlier versions only provide some of the features under other
+        // names.
+        let fp = has(b"hw.optional.floatingpoint\0");
+        let asimd = has(b"hw.optional.AdvSIMD\0") || has(b"hw.optional.neon\0");
+        let fp16 = has(b"hw.optional.arm.FEAT_FP16\0") || has(b"hw.optional.neon_fp16\0");
+        let aes = has(b"hw.optional.arm.FEAT_AES\0");
+        let pmull = has(b"hw.optional.arm.FEAT_PMULL\0");
+        let sha1 = has(b"hw.optional.arm.FEAT_SHA1\0");
+        let sha2 = has(b"hw.optional.arm.FEAT_SHA256\0");
+
+        enable_feature(Feature::fp, fp);
+        enable_feature(Feature::fp16, fp && fp16);
+        enable_feature(Feature::asimd, fp && asimd);
+        enable_feature(Feature::aes, aes);
+        enable_feature(Feature::pmull, pmull);
+        // Crypto is specified as AES + PMULL + SHA1 + SHA2 per LLVM/hosts.cpp
+        enable_feature(Feature::crypto, aes && pmull && sha1 && sha2);
+        enable_feature(
+            Feature::crc,
+            has(b"hw.optional.arm.FEAT_CRC32\0") || has(b"hw.optional.armv8_crc32\0"),
+        );
+        enable_feature(
+            Feature::lse,
+            has(b"hw.optional.arm.FEAT_LSE\0") || has(b"hw.optional.armv8_1_atomics\0"),
+        );
+        enable_feature(Feature::lse2, has(b"hw.optional.arm.FEAT_LSE2\0"));
+        enable_feature(Feature::rcpc, has(b"hw.optional.arm.FEAT_LRCPC\0"));
+        let dpb2 = has(b"hw.optional.arm.FEAT_DPB2\0");
+        enable_feature(Feature::dpb, dpb2 || has(b"hw.optional.arm.FEAT_DPB\0"));
+        enable_feature(Feature::dpb2, dpb2);
+        let flagm2 = has(b"hw.optional.arm.FEAT_FlagM2\0");
+        enable_feature(
+            Feature::flagm,
+            flagm2 || has(b"hw.optional.arm.FEAT_FlagM\0"),
+        );
+        enable_feature(Feature::flagm2, flagm2);
+        enable_feature(Feature::ecv, has(b"hw.optional.arm.FEAT_ECV\0"));
+        // SIMD extensions require SIMD support:
+        enable_feature(Feature::rdm, asimd && has(b"hw.optional.arm.FEAT_RDM\0"));
+        enable_feature(
+            Feature::dotprod,
+            asimd && has(b"hw.optional.arm.FEAT_DotProd\0"),
+        );
+        enable_feature(Feature::jsconv, fp && has(b"hw.optional.arm.FEAT_JSCVT\0"));
+        enable_feature(Feature::fcma, asimd && has(b"hw.optional.arm.FEAT_FCMA\0"));
+        let sha512 = asimd && sha1 && sha2 && has(b"hw.optional.arm.FEAT_SHA512\0");
+        enable_feature(Feature::sha512, sha512);
+        enable_feature(Feature::sha3, sha512 && has(b"hw.optional.arm.FEAT_SHA3\0"));
+        enable_feature(
+            Feature::fhm,
+            asimd
+                && fp16
+                && (has(b"hw.optional.arm.FEAT_FHM\0") || has(b"hw.optional.armv8_2_fhm\0")),
+        );
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A few of the `hw.optional.*` sysctls that are set on an Apple M1
+    /// running macOS 12.
+    const APPLE_M1: &[&[u8]] = &[
+        b"hw.optional.floatingpoint\0",
+        b"hw.optional.AdvSIMD\0",
+        b"hw.optional.neon\0",
+        b"hw.optional.neon_fp16\0",
+        b"hw.optional.armv8_crc32\0",
+        b"hw.optional.armv8_1_atomics\0",
+        b"hw.optional.arm.FEAT_FP16\0",
+        b"hw.optional.arm.FEAT_AES\0",
+        b"hw.optional.arm.FEAT_PMULL\0",
+        b"hw.optional.arm.FEAT_SHA1\0",
+        b"hw.optional.arm.FEAT_SHA256\0",
+        b"hw.optional.arm.FEAT_SHA512\0",
+        b"hw.optional.arm.FEAT_SHA3\0",
+        b"hw.optional.arm.FEAT_CRC32\0",
+        b"hw.optional.arm.FEAT_LSE\0",
+        b"hw.optional.arm.FEAT_LSE2\0",
+        b"hw.optional.arm.FEAT_LRCPC\0",
+        b"hw.optional.arm.FEAT_RDM\0",
+        b"hw.optional.arm.FEAT_DotProd\0",
+        b"hw.optional.arm.FEAT_JSCVT\0",
+        b"hw.optional.arm.FEAT_FCMA\0",
+        b"hw.optional.arm.FEAT_DPB\0",
+        b"hw.optional.arm.FEAT_DPB2\0",
+        b"hw.optional.arm.FEAT_FlagM\0",
+        b"hw.optional.arm.FEAT_FlagM2\0",
+        b"hw.optional.arm.FEAT_FHM\0",
+    ];
+
+    fn detect(sysctls: &[&[u8]]) -> cache::Initializer {
+        detect_features_with(|name| sysctls.contains(&name))
+    }
+
+    #[test]
+    fn apple_m1() {
+        let value = detect(APPLE_M1);
+        for &f in &[
+            Feature::fp,
+            Feature::fp16,
+            Feature::asimd,
+            Feature::aes,
+            Feature::pmull,
+            Feature::crc,
+            Feature::crypto,
+            Feature::lse,
+            Feature::lse2,
+            Feature::rdm,
+            Feature::rcpc,
+            Feature::dotprod,
+            Feature::fhm,
+            Feature::sha512,
+            Feature::sha3,
+            Feature::fcma,
+            Feature::jsconv,
+            Feature::dpb,
+            Feature::dpb2,
+            Feature::flagm,
+            Feature::flagm2,
+        ] {
+            assert!(value.test(f as u32), "{} not detected", f.to_str());
+        }
+        // FEAT_ECV was added with the M2:
+        for &f in &[Feature::sve, Feature::tme, Feature::ecv] {
+            assert!(!value.test(f as u32), "{} detected", f.to_str());
+        }
+    }
+
+    /// Feeds the same capability, CRC32 on a core with FP and AdvSIMD,
+    /// through the sysctl and the system register decoders, which must report
+    /// exactly the same features.
+    #[test]
+    fn crc_is_consistent_with_system_registers() {
+        use crate::detect::aarch64::{parse_system_registers, AA64Reg};
+
+        let base: &[&[u8]] = &[b"hw.optional.floatingpoint\0", b"hw.optional.AdvSIMD\0"];
+        // ID_AA64PFR0_EL1 is 0: FP and AdvSIMD are implemented.
+        let regs = |crc: bool| {
+            parse_system_registers(AA64Reg {
+                aa64isar0: (crc as u64) << 16,
+                ..AA64Reg::default()
+            })
+        };
+        assert_eq!(detect(base), regs(false));
+        for &name in &[
+            &b"hw.optional.arm.FEAT_CRC32\0"[..],
+            &b"hw.optional.armv8_crc32\0"[..],
+        ] {
+            let sysctls = [base[0], base[1], name];
+            assert_eq!(detect(&sysctls), regs(true));
+        }
+    }
+
+    #[test]
+    fn lse2() {
+        let sysctls: &[&[u8]] = &[b"hw.optional.arm.FEAT_LSE\0"];
+        let value = detect(sysctls);
+        assert!(value.test(Feature::lse as u32));
+        assert!(!value.test(Feature::lse2 as u32));
+
+        let sysctls: &[&[u8]] = &[b"hw.optional.arm.FEAT_LSE2\0"];
+        assert!(detect(sysctls).test(Feature::lse2 as u32));
+    }
+
+    #[test]
+    fn ecv() {
+        let sysctls: &[&[u8]] = &[b"hw.optional.arm.FEAT_ECV\0"];
+        assert!(detect(sysctls).test(Feature::ecv as u32));
+    }
+
+    /// Every sysctl that `detect_features` checks.
+    const ALL_SYSCTLS: &[&[u8]] = &[
+        b"hw.optional.floatingpoint\0",
+        b"hw.optional.AdvSIMD\0",
+        b"hw.optional.neon\0",
+        b"hw.optional.arm.FEAT_FP16\0",
+        b"hw.optional.neon_fp16\0",
+        b"hw.optional.arm.FEAT_AES\0",
+        b"hw.optional.arm.FEAT_PMULL\0",
+        b"hw.optional.arm.FEAT_SHA1\0",
+        b"hw.optional.arm.FEAT_SHA256\0",
+        b"hw.optional.arm.FEAT_CRC32\0",
+        b"hw.optional.armv8_crc32\0",
+        b"hw.optional.arm.FEAT_LSE\0",
+        b"hw.optional.armv8_1_atomics\0",
+        b"hw.optional.arm.FEAT_LSE2\0",
+        b"hw.optional.arm.FEAT_LRCPC\0",
+        b"hw.optional.arm.FEAT_DPB2\0",
+        b"hw.optional.arm.FEAT_DPB\0",
+        b"hw.optional.arm.FEAT_FlagM2\0",
+        b"hw.optional.arm.FEAT_FlagM\0",
+        b"hw.optional.arm.FEAT_ECV\0",
+        b"hw.optional.arm.FEAT_RDM\0",
+        b"hw.optional.arm.FEAT_DotProd\0",
+        b"hw.optional.arm.FEAT_JSCVT\0",
+        b"hw.optional.arm.FEAT_FCMA\0",
+        b"hw.optional.arm.FEAT_SHA512\0",
+        b"hw.optional.arm.FEAT_SHA3\0",
+        b"hw.optional.arm.FEAT_FHM\0",
+        b"hw.optional.armv8_2_fhm\0",
+    ];
+
+    /// The sysctls that are set on macOS 11, which predates the
+    /// `hw.optional.arm.FEAT_*` names.
+    const MACOS_11: &[&[u8]] = &[
+        b"hw.optional.floatingpoint\0",
+        b"hw.optional.neon\0",
+        b"hw.optional.neon_fp16\0",
+        b"hw.optional.armv8_crc32\0",
+        b"hw.optional.armv8_1_atomics\0",
+    ];
+
+    #[test]
+    fn macos_11() {
+        let value = detect(MACOS_11);
+        for &f in &[
+            Feature::fp,
+            Feature::fp16,
+            Feature::asimd,
+            Feature::crc,
+            Feature::lse,
+        ] {
+            assert!(value.test(f as u32), "{} not detected", f.to_str());
+        }
+        assert!(!value.test(Feature::aes as u32));
+    }
+
+    #[test]
+    fn sysctl_calls_are_bounded() {
+        // The most sysctls that `detect_features` may read: every name that it
+        // checks, once. Checking another name requires adding it to
+        // `ALL_SYSCTLS`.
+        let max_sysctl_calls = ALL_SYSCTLS.len();
+        assert_eq!(max_sysctl_calls, 28);
+        for (i, name) in ALL_SYSCTLS.iter().enumerate() {
+            assert!(!ALL_SYSCTLS[..i].contains(name), "{:?} listed twice", name);
+        }
+
+        for &sysctls in &[&[][..], ALL_SYSCTLS, APPLE_M1, MACOS_11] {
+            let names = std::cell::RefCell::new(std::vec::Vec::new());
+            detect_features_with(|name| {
+                names.borrow_mut().push(name.to_vec());
+                sysctls.contains(&name)
+            });
+            let names = names.into_inner();
+            assert!(names.len() <= max_sysctl_calls, "{} sysctls", names.len());
+            for (i, name) in names.iter().enumerate() {
+                assert!(
+                    ALL_SYSCTLS.contains(&&name[..]),
+                    "{:?} not in ALL_SYSCTLS",
+                    name
+                );
+                assert!(!names[..i].contains(name), "{:?} read twice", name);
+            }
+        }
+    }
+
+    #[test]
+    fn host() {
+        assert_eq!(detect_features(), detect_features_with(sysctl_enabled));
+        assert!(!sysctl_enabled(b"hw.optional.arm.FEAT_NOT_A_FEATURE\0"));
+    }
+}
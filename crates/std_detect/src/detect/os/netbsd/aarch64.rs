@@ -0,0 +1,201 @@
+//! Run-time feature detection for Aarch64 on NetBSD.
+//!
+//! NetBSD >= 9.0 provides the values of the ID registers of each core through
+//! the `machdep.cpuN.cpu_id` sysctls.
+
+use crate::detect::aarch64::{common_features, disable_non_uniform_sve, AA64Reg};
+use crate::detect::{cache, Feature};
+
+/// Try to read the features from the system registers of every core.
+///
+/// On asymmetric SoCs only the features that are supported by every core are
+/// reported, and `sve` is only reported if every core reports the same
+/// `ID_AA64ZFR0_EL1`. `ZCR_EL1.LEN` cannot be read from userspace and NetBSD
+/// does not report the vector lengths, so cores with different SVE vector
+/// lengths are not detected.
+pub(crate) fn detect_features() -> cache::Initializer {
+    // `struct aarch64_sysctl_cpu_id` has grown over time, so leave room for
+    // the fields added by newer versions.
+    let mut buf = [0_u8; 256];
+    let mut cpu = 0;
+    let cores = core::iter::from_fn(|| {
+        let regs = read_cpu_id(cpu, &mut buf).map(cpu_regs)?;
+        cpu += 1;
+        Some(regs)
+    });
+    let mut value = common_features(cores);
+    cache::set_detection_source(cache::DetectionSource::Sysctl);
+    if value.test(Feature::sve as u32) {
+        let mut cpu = 0;
+        let aa64zfr0s = core::iter::from_fn(|| {
+            let aa64zfr0 = read_cpu_id(cpu, &mut buf).map(cpu_aa64zfr0)?;
+            cpu += 1;
+            Some(aa64zfr0)
+        });
+        disable_non_uniform_sve(&mut value, aa64zfr0s);
+    }
+    value
+}
+
+/// Reads the `machdep.cpuN.cpu_id` sysctl of the core `cpu` into `buf`.
+///
+/// Returns `None` if there is no such core.
+fn read_cpu_id(cpu: usize, buf: &mut [u8; 256]) -> Option<&[u8]> {
+    let mut name = [0_u8; 40];
+    let name = cpu_id_name(cpu, &mut name);
+    let mut len = buf.len();
+    let ret = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr() as *const libc::c_char,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            &mut len,
+            core::ptr::null(),
+            0,
+        )
+    };
+    if ret == -1 {
+        return None;
+    }
+    Some(&buf[..len.min(buf.len())])
+}
+
+/// Writes the NUL-terminated name of the `machdep.cpuN.cpu_id` sysctl of the
+/// core `cpu` into `name`.
+fn cpu_id_name(cpu: usize, name: &mut [u8; 40]) -> &[u8] {
+    let mut digits = [0_u8; 20];
+    let mut i = digits.len();
+    let mut n = cpu;
+    loop {
+        i -= 1;
+        digits[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    let mut len = 0;
+    for part in &[&b"machdep.cpu"[..], &digits[i..], b".cpu_id\0"] {
+        name[len..len + part.len()].copy_from_slice(part);
+        len += part.len();
+    }
+    &name[..len]
+}
+
+// The indices of the fields of `struct aarch64_sysctl_cpu_id`, see
+// https://github.com/NetBSD/src/blob/trunk/sys/arch/aarch64/include/armreg.h.
+// All of them are `uint64_t`s that have been part of the struct since
+// NetBSD 9.0.
+const AC_AA64ISAR0: usize = 5;
+const AC_AA64ISAR1: usize = 6;
+const AC_AA64MMFR0: usize = 7;
+const AC_AA64MMFR2: usize = 9;
+const AC_AA64PFR0: usize = 10;
+const AC_AA64ZFR0: usize = 12;
+
+/// Reads the `i`-th `uint64_t` field of a `struct aarch64_sysctl_cpu_id`.
+fn cpu_id_field(buf: &[u8], i: usize) -> Option<u64> {
+    let bytes = buf.get(i * 8..i * 8 + 8)?;
+    let mut b = [0_u8; 8];
+    b.copy_from_slice(bytes);
+    Some(u64::from_ne_bytes(b))
+}
+
+/// Extracts the registers from a `struct aarch64_sysctl_cpu_id`.
+fn parse_cpu_id(buf: &[u8]) -> Option<AA64Reg> {
+    Some(AA64Reg {
+        aa64isar0: cpu_id_field(buf, AC_AA64ISAR0)?,
+        aa64isar1: cpu_id_field(buf, AC_AA64ISAR1)?,
+        aa64mmfr0: cpu_id_field(buf, AC_AA64MMFR0)?,
+        aa64mmfr2: cpu_id_field(buf, AC_AA64MMFR2)?,
+        aa64pfr0: cpu_id_field(buf, AC_AA64PFR0)?,
+    })
+}
+
+/// The registers of a core that implements no features, not even FP and
+/// AdvSIMD, which an `ID_AA64PFR0_EL1` of 0 would report.
+const NO_FEATURES: AA64Reg = AA64Reg {
+    aa64isar0: 0,
+    aa64isar1: 0,
+    aa64mmfr0: 0,
+    aa64mmfr2: 0,
+    aa64pfr0: (0xf << 16) | (0xf << 20),
+};
+
+/// Extracts the registers from a `struct aarch64_sysctl_cpu_id`. A core whose
+/// registers cannot be parsed supports no features.
+fn cpu_regs(buf: &[u8]) -> AA64Reg {
+    parse_cpu_id(buf).unwrap_or(NO_FEATURES)
+}
+
+/// Extracts the `ID_AA64ZFR0_EL1` of a core, which describes its SVE
+/// features, from a `struct aarch64_sysctl_cpu_id`, or `None` if the core
+/// does not implement SVE.
+fn cpu_aa64zfr0(buf: &[u8]) -> Option<u64> {
+    // ID_AA64PFR0_EL1.SVE[35:32]:
+    if (cpu_id_field(buf, AC_AA64PFR0)? >> 32) & 0xf == 0 {
+        return None;
+    }
+    cpu_id_field(buf, AC_AA64ZFR0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu_id() -> [u8; 8 * 19] {
+        let mut buf = [0_u8; 8 * 19];
+        for (i, chunk) in buf.chunks_mut(8).enumerate() {
+            chunk.copy_from_slice(&(i as u64 + 1).to_ne_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn parse() {
+        let buf = cpu_id();
+        let regs = parse_cpu_id(&buf).unwrap();
+        assert_eq!(regs.aa64isar0, 6);
+        assert_eq!(regs.aa64isar1, 7);
+        assert_eq!(regs.aa64mmfr0, 8);
+        assert_eq!(regs.aa64mmfr2, 10);
+        assert_eq!(regs.aa64pfr0, 11);
+
+        assert!(parse_cpu_id(&buf[..8 * 11]).is_some());
+        assert!(parse_cpu_id(&buf[..8 * 11 - 1]).is_none());
+        assert!(parse_cpu_id(&[]).is_none());
+    }
+
+    #[test]
+    fn unparsable() {
+        use crate::detect::aarch64::parse_system_registers;
+
+        let value = common_features(core::iter::once(cpu_regs(&[])));
+        assert_eq!(value, parse_system_registers(NO_FEATURES));
+        for bit in 0..Feature::_last as u32 {
+            assert!(!value.test(bit), "{} detected", bit);
+        }
+        // The other cores do not add features either:
+        let buf = cpu_id();
+        let cores = [cpu_regs(&buf), cpu_regs(&buf[..8])];
+        assert!(!common_features(cores.iter().cloned()).test(Feature::fp as u32));
+    }
+
+    #[test]
+    fn sve() {
+        let mut buf = cpu_id();
+        // ID_AA64PFR0_EL1.SVE is 0:
+        assert_eq!(cpu_aa64zfr0(&buf), None);
+        let pfr0 = 1_u64 << 32;
+        buf[AC_AA64PFR0 * 8..AC_AA64PFR0 * 8 + 8].copy_from_slice(&pfr0.to_ne_bytes());
+        assert_eq!(cpu_aa64zfr0(&buf), Some(13));
+        assert_eq!(cpu_aa64zfr0(&buf[..8 * 13 - 1]), None);
+    }
+
+    #[test]
+    fn name() {
+        let mut name = [0_u8; 40];
+        assert_eq!(cpu_id_name(0, &mut name), b"machdep.cpu0.cpu_id\0");
+        assert_eq!(cpu_id_name(12, &mut name), b"machdep.cpu12.cpu_id\0");
+        assert!(cpu_id_name(usize::MAX, &mut name).ends_with(b"5.cpu_id\0"));
+    }
+}
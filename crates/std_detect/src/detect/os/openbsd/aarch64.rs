@@ -0,0 +1,59 @@
+//! Run-time feature detection for Aarch64 on OpenBSD.
+//!
+//! OpenBSD >= 7.1 provides the values of the ID registers, sanitized across
+//! all cores, through `sysctl`.
+
+use crate::detect::aarch64::{parse_system_registers, AA64Reg};
+use crate::detect::cache;
+
+// These values are part of the platform-specific [machine/cpu.h][cpu_h].
+//
+// [cpu_h]: https://github.com/openbsd/src/blob/master/sys/arch/arm64/include/cpu.h
+const CTL_MACHDEP: libc::c_int = 7;
+const CPU_ID_AA64ISAR0: libc::c_int = 2;
+const CPU_ID_AA64ISAR1: libc::c_int = 3;
+const CPU_ID_AA64MMFR0: libc::c_int = 5;
+const CPU_ID_AA64MMFR2: libc::c_int = 7;
+const CPU_ID_AA64PFR0: libc::c_int = 8;
+
+/// Try to read the features from the system registers.
+pub(crate) fn detect_features() -> cache::Initializer {
+    // ID_AA64ISAR0_EL1 is the first register that was exposed; if it is not
+    // available, none of them are.
+    let aa64isar0 = match sysctl64(CPU_ID_AA64ISAR0) {
+        Some(r) => r,
+        None => return cache::Initializer::default(),
+    };
+    cache::set_detection_source(cache::DetectionSource::Sysctl);
+    parse_system_registers(AA64Reg {
+        aa64isar0,
+        aa64isar1: sysctl64(CPU_ID_AA64ISAR1).unwrap_or(0),
+        aa64mmfr0: sysctl64(CPU_ID_AA64MMFR0).unwrap_or(0),
+        aa64mmfr2: sysctl64(CPU_ID_AA64MMFR2).unwrap_or(0),
+        // An ID_AA64PFR0_EL1 of 0 would mean that FP and AdvSIMD are
+        // implemented, so if it cannot be read report them as not
+        // implemented (0xf) instead:
+        aa64pfr0: sysctl64(CPU_ID_AA64PFR0).unwrap_or((0xf << 16) | (0xf << 20)),
+    })
+}
+
+/// Reads the `machdep.<id>` sysctl of type `u64`.
+fn sysctl64(id: libc::c_int) -> Option<u64> {
+    let mib = [CTL_MACHDEP, id];
+    let mut value: u64 = 0;
+    let mut len = core::mem::size_of::<u64>();
+    let ret = unsafe {
+        libc::sysctl(
+            mib.as_ptr(),
+            mib.len() as libc::c_uint,
+            &mut value as *mut u64 as *mut libc::c_void,
+            &mut len,
+            core::ptr::null_mut(),
+            0,
+        )
+    };
+    if ret == -1 || len != core::mem::size_of::<u64>() {
+        return None;
+    }
+    Some(value)
+}
@@ -24,78 +24,353 @@ use crate::detect::{bit, cache, Feature};
 /// [wiki_cpuid]: https://en.wikipedia.org/wiki/CPUID
 /// [intel64_ref]: http://www.intel.de/content/dam/www/public/us/en/documents/manuals/64-ia-32-architectures-software-developer-instruction-set-reference-manual-325383.pdf
 /// [amd64_ref]: http://support.amd.com/TechDocs/24594.pdf
-#[allow(clippy::similar_names)]
 pub(crate) fn detect_features() -> cache::Initializer {
-    let mut value = cache::Initializer::default();
-
     // If the x86 CPU does not support the CPUID instruction then it is too
-    // old to support any of the currently-detectable features.
+    // old to support any of the currently-detectable features. If the
+    // instruction faults, no features are detected either.
+    if !cpuid_usable() {
+        return cache::Initializer::default();
+    }
+    cache::set_detection_source(cache::DetectionSource::Cpuid);
+
+    // Calling `__cpuid_count` from here on is safe because the CPU has
+    // `cpuid` support. `_xgetbv` is only called if the OS has set `osxsave`.
+    let xgetbv = |xcr| unsafe { _xgetbv(xcr) };
+    // On Windows, only trust the state components of `XCR0` that the OS also
+    // reports as enabled:
+    #[cfg(target_os = "windows")]
+    let xgetbv = |xcr| {
+        let value = xgetbv(xcr);
+        if xcr == 0 {
+            windows_xcr0(value, enabled_xstate_features())
+        } else {
+            value
+        }
+    };
+    // On NetBSD, only trust the state components of `XCR0` that the kernel
+    // reports as enabled:
+    #[cfg(all(target_os = "netbsd", feature = "libc"))]
+    let xgetbv = |xcr| {
+        let value = xgetbv(xcr);
+        if xcr == 0 {
+            netbsd_xcr0(value, netbsd_xsave_features())
+        } else {
+            value
+        }
+    };
+    detect_features_with(
+        |leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) },
+        xgetbv,
+    )
+}
+
+/// Returns `true` if the `cpuid` instruction can be executed without faulting.
+///
+/// On `x86`, `has_cpuid` tests whether the ID flag (bit 21) of `EFLAGS` can be
+/// toggled before any CPUID is executed, so 486-class CPUs without CPUID do
+/// not fault here. On `x86_64` CPUID is always available, but on Linux a
+/// sandbox or a record-and-replay debugger can enable CPUID faulting for the
+/// thread, in which case executing `cpuid` raises `SIGSEGV`.
+fn cpuid_usable() -> bool {
     if !has_cpuid() {
-        return value;
+        return false;
+    }
+    #[cfg(all(target_os = "linux", target_arch = "x86_64", feature = "libc"))]
+    {
+        if crate::detect::cpuid_faulting::cpuid_faulting() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Computes the features from a dump of the `cpuid` instruction, given as
+/// `(leaf, sub_leaf, [eax, ebx, ecx, edx])`, and the value of `XCR0`.
+///
+/// Leaves that are not in `leaves` read as zeros.
+pub(crate) fn detect_features_from_dump(
+    leaves: &[(u32, u32, [u32; 4])],
+    xcr0: u64,
+) -> cache::Initializer {
+    detect_features_with(
+        |leaf, sub_leaf| {
+            let [eax, ebx, ecx, edx] = leaves
+                .iter()
+                .find(|l| l.0 == leaf && l.1 == sub_leaf)
+                .map_or([0; 4], |l| l.2);
+            CpuidResult { eax, ebx, ecx, edx }
+        },
+        |xcr| if xcr == 0 { xcr0 } else { 0 },
+    )
+}
+
+/// Returns the state components of `xcr0` that are also reported as enabled
+/// by `GetEnabledXStateFeatures`, or only the x87 and SSE state components if
+/// that function is not available (`None`).
+///
+/// Windows versions before Windows 7 SP1 do not save the AVX state on context
+/// switches, and `GetEnabledXStateFeatures` was added together with that
+/// support.
+#[cfg(any(test, target_os = "windows"))]
+fn windows_xcr0(xcr0: u64, enabled_xstate_features: Option<u64>) -> u64 {
+    // `XSTATE_MASK_LEGACY`:
+    const LEGACY_STATE_COMPONENTS: u64 = 0b11;
+    xcr0 & enabled_xstate_features.unwrap_or(LEGACY_STATE_COMPONENTS)
+}
+
+/// Calls `GetEnabledXStateFeatures` if `kernel32.dll` exports it.
+#[cfg(target_os = "windows")]
+fn enabled_xstate_features() -> Option<u64> {
+    type HMODULE = *mut core::ffi::c_void;
+    type FARPROC = Option<unsafe extern "system" fn() -> isize>;
+
+    extern "system" {
+        fn GetModuleHandleA(lpModuleName: *const u8) -> HMODULE;
+        fn GetProcAddress(hModule: HMODULE, lpProcName: *const u8) -> FARPROC;
+    }
+
+    // `GetEnabledXStateFeatures` is looked up at run-time, because linking
+    // against it would prevent the binary from starting on older versions.
+    unsafe {
+        let kernel32 = GetModuleHandleA(b"kernel32.dll\0".as_ptr());
+        if kernel32.is_null() {
+            return None;
+        }
+        let f = GetProcAddress(kernel32, b"GetEnabledXStateFeatures\0".as_ptr())?;
+        let get_enabled_xstate_features: unsafe extern "system" fn() -> u64 = mem::transmute(f);
+        Some(get_enabled_xstate_features())
     }
+}
+
+/// Returns the state components of `xcr0` that are also reported as enabled
+/// by the `machdep.xsave_features` sysctl, or `xcr0` if the sysctl is not
+/// available (`None`).
+///
+/// The kernel only enables the state components whose state it saves on
+/// context switches, which `xgetbv` can confirm on its own. The sysctl, added
+/// in NetBSD 9.0, guards against environments that report a different
+/// `XCR0` than the one the kernel uses, e.g., emulated `xgetbv`s.
+#[cfg(any(test, all(target_os = "netbsd", feature = "libc")))]
+fn netbsd_xcr0(xcr0: u64, xsave_features: Option<u64>) -> u64 {
+    xcr0 & xsave_features.unwrap_or(!0)
+}
 
-    // Calling `__cpuid`/`__cpuid_count` from here on is safe because the CPU
-    // has `cpuid` support.
+/// Reads the `machdep.xsave_features` sysctl.
+#[cfg(all(target_os = "netbsd", feature = "libc"))]
+fn netbsd_xsave_features() -> Option<u64> {
+    let mut value: u64 = 0;
+    let mut len = mem::size_of::<u64>();
+    let ret = unsafe {
+        libc::sysctlbyname(
+            b"machdep.xsave_features\0".as_ptr() as *const libc::c_char,
+            &mut value as *mut u64 as *mut libc::c_void,
+            &mut len,
+            core::ptr::null(),
+            0,
+        )
+    };
+    if ret == 0 && len == mem::size_of::<u64>() {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// The CPUID leaves, and the value of `XCR0`, that the x86 features are
+/// decoded from.
+///
+/// The fields of leaves that the processor does not support, and `xcr0` if
+/// the OS has not set `osxsave`, are zero.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct CpuidLeaves {
+    /// CPUID.0:EAX, the highest basic leaf.
+    pub(crate) max_basic_leaf: u32,
+    /// CPUID.0:EBX, EDX, and ECX, in that order.
+    pub(crate) vendor_id: [u8; 12],
+    /// CPUID.1:ECX and EDX, "Processor Info and Feature Bits".
+    pub(crate) proc_info_ecx: u32,
+    pub(crate) proc_info_edx: u32,
+    /// CPUID.(EAX=7,ECX=0):EBX, ECX, and EDX, "Extended Features".
+    pub(crate) extended_features_ebx: u32,
+    pub(crate) extended_features_ecx: u32,
+    pub(crate) extended_features_edx: u32,
+    /// CPUID.(EAX=7,ECX=1):EAX and EDX.
+    pub(crate) extended_features1_eax: u32,
+    pub(crate) extended_features1_edx: u32,
+    /// CPUID.(EAX=24H,ECX=0):EBX, "AVX10 Converged Vector ISA".
+    pub(crate) avx10_ebx: u32,
+    /// CPUID.(EAX=0DH,ECX=0):EDX:EAX, the state components that the
+    /// processor supports saving with `XSAVE`.
+    pub(crate) proc_extended_state_components: u64,
+    /// CPUID.(EAX=0DH,ECX=1):EAX.
+    pub(crate) proc_extended_state1_eax: u32,
+    /// The value of `XCR0`.
+    pub(crate) xcr0: u64,
+    /// CPUID.8000_0000H:EAX, the highest extended leaf.
+    pub(crate) extended_max_basic_leaf: u32,
+    /// CPUID.8000_0001H:ECX and EDX, "Extended Processor Info and Feature
+    /// Bits".
+    pub(crate) extended_proc_info_ecx: u32,
+    pub(crate) extended_proc_info_edx: u32,
+    /// CPUID.8000_0007H:EDX, "Advanced Power Management Information".
+    pub(crate) apm_edx: u32,
+}
+
+/// Computes the features from the results of the `cpuid` and `xgetbv`
+/// instructions, which are provided by the `cpuid(leaf, sub_leaf)` and
+/// `xgetbv(xcr)` functions.
+fn detect_features_with(
+    cpuid: impl FnMut(u32, u32) -> CpuidResult,
+    xgetbv: impl FnMut(u32) -> u64,
+) -> cache::Initializer {
+    decode_x86_features(&read_cpuid_leaves(cpuid, xgetbv))
+}
+
+/// Reads the CPUID leaves that the features are decoded from.
+///
+/// Each required CPUID leaf is queried exactly once, and `xgetbv` is only
+/// called if the OS has set `osxsave`. That is at most 10 `cpuid` calls and
+/// one `xgetbv` call, whatever the CPU reports, which the
+/// `detection_cost_is_bounded` test enforces.
+#[allow(clippy::similar_names)]
+fn read_cpuid_leaves(
+    mut cpuid: impl FnMut(u32, u32) -> CpuidResult,
+    mut xgetbv: impl FnMut(u32) -> u64,
+) -> CpuidLeaves {
+    let mut leaves = CpuidLeaves::default();
 
     // 0. EAX = 0: Basic Information:
     // - EAX returns the "Highest Function Parameter", that is, the maximum
     // leaf value for subsequent calls of `cpuinfo` in range [0,
     // 0x8000_0000]. - The vendor ID is stored in 12 u8 ascii chars,
     // returned in EBX, EDX, and   ECX (in that order):
-    let (max_basic_leaf, vendor_id) = unsafe {
-        let CpuidResult {
-            eax: max_basic_leaf,
-            ebx,
-            ecx,
-            edx,
-        } = __cpuid(0);
-        let vendor_id: [[u8; 4]; 3] = [
+    let CpuidResult { eax, ebx, ecx, edx } = cpuid(0, 0);
+    leaves.max_basic_leaf = eax;
+    let vendor_id: [[u8; 4]; 3] = unsafe {
+        [
             mem::transmute(ebx),
             mem::transmute(edx),
             mem::transmute(ecx),
-        ];
-        let vendor_id: [u8; 12] = mem::transmute(vendor_id);
-        (max_basic_leaf, vendor_id)
+        ]
     };
+    leaves.vendor_id = unsafe { mem::transmute(vendor_id) };
 
-    if max_basic_leaf < 1 {
+    if leaves.max_basic_leaf < 1 {
         // Earlier Intel 486, CPUID not implemented
-        return value;
+        return leaves;
     }
 
     // EAX = 1, ECX = 0: Queries "Processor Info and Feature Bits";
     // Contains information about most x86 features.
-    let CpuidResult {
-        ecx: proc_info_ecx,
-        edx: proc_info_edx,
-        ..
-    } = unsafe { __cpuid(0x0000_0001_u32) };
+    let CpuidResult { ecx, edx, .. } = cpuid(0x0000_0001_u32, 0);
+    leaves.proc_info_ecx = ecx;
+    leaves.proc_info_edx = edx;
 
     // EAX = 7, ECX = 0: Queries "Extended Features";
     // Contains information about bmi,bmi2, and avx2 support.
-    let (extended_features_ebx, extended_features_ecx) = if max_basic_leaf >= 7 {
-        let CpuidResult { ebx, ecx, .. } = unsafe { __cpuid(0x0000_0007_u32) };
-        (ebx, ecx)
-    } else {
-        (0, 0) // CPUID does not support "Extended Features"
-    };
+    let mut extended_features_max_sub_leaf = 0;
+    if leaves.max_basic_leaf >= 7 {
+        let CpuidResult { eax, ebx, ecx, edx } = cpuid(0x0000_0007_u32, 0);
+        extended_features_max_sub_leaf = eax;
+        leaves.extended_features_ebx = ebx;
+        leaves.extended_features_ecx = ecx;
+        leaves.extended_features_edx = edx;
+    }
+
+    // EAX = 7, ECX = 1: Queries "Extended Features" sub-leaf 1;
+    // Contains information about avx512bf16 and AVX10 support.
+    if leaves.max_basic_leaf >= 7 && extended_features_max_sub_leaf >= 1 {
+        let CpuidResult { eax, edx, .. } = cpuid(0x0000_0007_u32, 1);
+        leaves.extended_features1_eax = eax;
+        leaves.extended_features1_edx = edx;
+    }
+
+    // EAX = 24H, ECX = 0: Queries "AVX10 Converged Vector ISA", which is only
+    // defined if CPUID.(EAX=07H,ECX=01H):EDX[19] is set;
+    // - EBX[7:0]: AVX10 version,
+    // - EBX[16], EBX[17], EBX[18]: support for 128-bit, 256-bit, and 512-bit
+    // vectors.
+    if leaves.max_basic_leaf >= 0x24 && bit::test(leaves.extended_features1_edx as usize, 19) {
+        let CpuidResult { ebx, .. } = cpuid(0x0000_0024_u32, 0);
+        leaves.avx10_ebx = ebx;
+    }
+
+    // EAX = 0DH, ECX = 0: Queries "Processor Extended State Enumeration
+    // Main Leaf";
+    // - EAX and EDX contain the bitmap of the state components that the
+    // processor supports saving/restoring with `XSAVE` (i.e., the bits that
+    // can be set in `XCR0`).
+    //
+    // EAX = 0DH, ECX = 1: Queries "Processor Extended State Enumeration
+    // Sub-leaf";
+    // Contains information about xsaveopt, xsavec, and xsaves support.
+    if leaves.max_basic_leaf >= 0xd {
+        let CpuidResult { eax, edx, .. } = cpuid(0xd_u32, 0);
+        leaves.proc_extended_state_components = (u64::from(edx) << 32) | u64::from(eax);
+        let CpuidResult { eax, .. } = cpuid(0xd_u32, 1);
+        leaves.proc_extended_state1_eax = eax;
+    }
+
+    // `XGETBV` raises #UD unless the CPU supports `XSAVE` and the OS has set
+    // `OSXSAVE` (CR4.OSXSAVE[bit 18]):
+    if bit::test(leaves.proc_info_ecx as usize, 26) && bit::test(leaves.proc_info_ecx as usize, 27)
+    {
+        leaves.xcr0 = xgetbv(0);
+    }
 
     // EAX = 0x8000_0000, ECX = 0: Get Highest Extended Function Supported
     // - EAX returns the max leaf value for extended information, that is,
     // `cpuid` calls in range [0x8000_0000; u32::MAX]:
-    let CpuidResult {
-        eax: extended_max_basic_leaf,
-        ..
-    } = unsafe { __cpuid(0x8000_0000_u32) };
+    leaves.extended_max_basic_leaf = cpuid(0x8000_0000_u32, 0).eax;
 
     // EAX = 0x8000_0001, ECX=0: Queries "Extended Processor Info and Feature
     // Bits"
-    let extended_proc_info_ecx = if extended_max_basic_leaf >= 1 {
-        let CpuidResult { ecx, .. } = unsafe { __cpuid(0x8000_0001_u32) };
-        ecx
-    } else {
-        0
-    };
+    if leaves.extended_max_basic_leaf >= 0x8000_0001_u32 {
+        let CpuidResult { ecx, edx, .. } = cpuid(0x8000_0001_u32, 0);
+        leaves.extended_proc_info_ecx = ecx;
+        leaves.extended_proc_info_edx = edx;
+    }
+
+    // EAX = 0x8000_0007: Queries "Advanced Power Management Information".
+    if leaves.extended_max_basic_leaf >= 0x8000_0007_u32 {
+        leaves.apm_edx = cpuid(0x8000_0007_u32, 0).edx;
+    }
+
+    leaves
+}
+
+/// Decodes the features from already-read CPUID leaves.
+///
+/// This performs no hardware access, and any `leaves` can be decoded, so it
+/// can be tested and fuzzed against arbitrary CPUID dumps.
+#[allow(clippy::similar_names)]
+pub(crate) fn decode_x86_features(leaves: &CpuidLeaves) -> cache::Initializer {
+    let mut value = cache::Initializer::default();
+
+    let CpuidLeaves {
+        max_basic_leaf,
+        vendor_id,
+        proc_info_ecx,
+        proc_info_edx,
+        extended_features_ebx,
+        extended_features_ecx,
+        extended_features_edx,
+        extended_features1_eax,
+        extended_features1_edx: _,
+        avx10_ebx,
+        proc_extended_state_components,
+        proc_extended_state1_eax,
+        xcr0,
+        extended_max_basic_leaf,
+        extended_proc_info_ecx,
+        extended_proc_info_edx,
+        apm_edx,
+    } = *leaves;
+
+    if max_basic_leaf < 1 {
+        // Earlier Intel 486, CPUID not implemented
+        return value;
+    }
 
     {
         // borrows value till the end of this scope:
@@ -111,13 +386,17 @@ pub(crate) fn detect_features() -> cache::Initializer {
         enable(proc_info_ecx, 13, Feature::cmpxchg16b);
         enable(proc_info_ecx, 19, Feature::sse4_1);
         enable(proc_info_ecx, 20, Feature::sse4_2);
+        enable(proc_info_ecx, 22, Feature::movbe);
         enable(proc_info_ecx, 23, Feature::popcnt);
         enable(proc_info_ecx, 25, Feature::aes);
-        enable(proc_info_ecx, 29, Feature::f16c);
         enable(proc_info_ecx, 30, Feature::rdrand);
         enable(extended_features_ebx, 18, Feature::rdseed);
         enable(extended_features_ebx, 19, Feature::adx);
         enable(extended_features_ebx, 11, Feature::rtm);
+        enable(extended_features_ebx, 23, Feature::clflushopt);
+        enable(extended_features_ebx, 24, Feature::clwb);
+        enable(extended_features1_eax, 31, Feature::movrs);
+        enable(proc_info_edx, 0, Feature::x87);
         enable(proc_info_edx, 4, Feature::tsc);
         enable(proc_info_edx, 23, Feature::mmx);
         enable(proc_info_edx, 24, Feature::fxsr);
@@ -128,6 +407,10 @@ pub(crate) fn detect_features() -> cache::Initializer {
         enable(extended_features_ebx, 3, Feature::bmi1);
         enable(extended_features_ebx, 8, Feature::bmi2);
 
+        // GFNI also has SSE encodings, which some CPUs without AVX (e.g.
+        // Tremont) implement, so it is not gated on the AVX state:
+        enable(extended_features_ecx, 8, Feature::gfni);
+
         // `XSAVE` and `AVX` support:
         let cpu_xsave = bit::test(proc_info_ecx as usize, 26);
         if cpu_xsave {
@@ -143,6 +426,7 @@ pub(crate) fn detect_features() -> cache::Initializer {
             // [is_avx_enabled]: https://software.intel.com/en-us/blogs/2011/04/14/is-avx-enabled
             // [mozilla_sse_cpp]: https://hg.mozilla.org/mozilla-central/file/64bab5cbb9b6/mozglue/build/SSE.cpp#l190
             let cpu_osxsave = bit::test(proc_info_ecx as usize, 27);
+            enable(proc_info_ecx, 27, Feature::osxsave);
 
             if cpu_osxsave {
                 // 2. The OS must have signaled the CPU that it supports saving and
@@ -154,13 +438,18 @@ pub(crate) fn detect_features() -> cache::Initializer {
                 //
                 // by setting the corresponding bits of `XCR0` to `1`.
                 //
-                // This is safe because the CPU supports `xsave`
-                // and the OS has set `osxsave`.
-                let xcr0 = unsafe { _xgetbv(0) };
+                // `xcr0` was read because the CPU supports `xsave` and the
+                // OS has set `osxsave`.
+                // A state component is only usable if the CPU enumerates it
+                // in CPUID leaf 0xD _and_ the OS has enabled it in `XCR0`.
+                let os_state_supported = |component_bits: u64| -> bool {
+                    let enabled = proc_extended_state_components & xcr0;
+                    enabled & component_bits == component_bits
+                };
                 // Test `XCR0.SSE[1]` and `XCR0.AVX[2]` with the mask `0b110 == 6`:
-                let os_avx_support = xcr0 & 6 == 6;
+                let os_avx_support = os_state_supported(6);
                 // Test `XCR0.AVX-512[7:5]` with the mask `0b1110_0000 == 224`:
-                let os_avx512_support = xcr0 & 224 == 224;
+                let os_avx512_support = os_state_supported(224);
 
                 // Only if the OS and the CPU support saving/restoring the AVX
                 // registers we enable `xsave` support:
@@ -177,52 +466,77 @@ pub(crate) fn detect_features() -> cache::Initializer {
                     //
                     enable(proc_info_ecx, 26, Feature::xsave);
 
-                    // For `xsaveopt`, `xsavec`, and `xsaves` we need to query:
+                    // For `xsaveopt`, `xsavec`, and `xsaves` we need the
                     // Processor Extended State Enumeration Sub-leaf (EAX = 0DH,
                     // ECX = 1):
-                    if max_basic_leaf >= 0xd {
-                        let CpuidResult {
-                            eax: proc_extended_state1_eax,
-                            ..
-                        } = unsafe { __cpuid_count(0xd_u32, 1) };
-                        enable(proc_extended_state1_eax, 0, Feature::xsaveopt);
-                        enable(proc_extended_state1_eax, 1, Feature::xsavec);
-                        enable(proc_extended_state1_eax, 3, Feature::xsaves);
-                    }
+                    enable(proc_extended_state1_eax, 0, Feature::xsaveopt);
+                    enable(proc_extended_state1_eax, 1, Feature::xsavec);
+                    enable(proc_extended_state1_eax, 3, Feature::xsaves);
 
-                    // FMA (uses 256-bit wide registers):
+                    // FMA and F16C (use 256-bit wide registers):
                     enable(proc_info_ecx, 12, Feature::fma);
+                    enable(proc_info_ecx, 29, Feature::f16c);
 
                     // And AVX/AVX2:
                     enable(proc_info_ecx, 28, Feature::avx);
                     enable(extended_features_ebx, 5, Feature::avx2);
 
+                    // The VEX-encoded VAES and VPCLMULQDQ instructions only
+                    // need the AVX state, the EVEX-encoded ones are covered by
+                    // the AVX-512 features below:
+                    enable(extended_features_ecx, 9, Feature::vaes);
+                    enable(extended_features_ecx, 10, Feature::vpclmulqdq);
+
                     // For AVX-512 the OS also needs to support saving/restoring
-                    // the extended state, only then we enable AVX-512 support:
-                    if os_avx512_support {
+                    // the extended state, only then we enable AVX-512 support.
+                    //
+                    // On hybrid processors, CPUID.(EAX=07H,ECX=0):EDX[15], the
+                    // leaves describe the core that executes `cpuid`, so the
+                    // 512-bit features that not every core needs to support,
+                    // that is, the legacy AVX-512 features and
+                    // `avx10.1-512`, are not reported:
+                    let hybrid = bit::test(extended_features_edx as usize, 15);
+                    if os_avx512_support && !hybrid {
                         enable(extended_features_ebx, 16, Feature::avx512f);
                         enable(extended_features_ebx, 17, Feature::avx512dq);
                         enable(extended_features_ebx, 21, Feature::avx512ifma);
                         enable(extended_features_ebx, 26, Feature::avx512pf);
                         enable(extended_features_ebx, 27, Feature::avx512er);
+                        enable(extended_features_edx, 2, Feature::avx5124vnniw);
+                        enable(extended_features_edx, 3, Feature::avx5124fmaps);
                         enable(extended_features_ebx, 28, Feature::avx512cd);
                         enable(extended_features_ebx, 30, Feature::avx512bw);
                         enable(extended_features_ebx, 31, Feature::avx512vl);
                         enable(extended_features_ecx, 1, Feature::avx512vbmi);
-                        enable(extended_features_ecx, 5, Feature::avx512bf16);
+                        enable(extended_features1_eax, 5, Feature::avx512bf16);
                         enable(extended_features_ecx, 6, Feature::avx512vbmi2);
                         enable(extended_features_ecx, 8, Feature::avx512gfni);
-                        enable(extended_features_ecx, 8, Feature::avx512vp2intersect);
+                        enable(extended_features_edx, 8, Feature::avx512vp2intersect);
                         enable(extended_features_ecx, 9, Feature::avx512vaes);
                         enable(extended_features_ecx, 10, Feature::avx512vpclmulqdq);
                         enable(extended_features_ecx, 11, Feature::avx512vnni);
                         enable(extended_features_ecx, 12, Feature::avx512bitalg);
                         enable(extended_features_ecx, 14, Feature::avx512vpopcntdq);
                     }
+
+                    // AVX10 also needs the AVX-512 state at 256-bit vector
+                    // length. Processors that only support 256-bit vectors do
+                    // not set the legacy AVX-512 bits above, so they are only
+                    // reported as `avx10.1-256`, which does not imply any of
+                    // the 512-bit features. Every AVX10 core of a hybrid
+                    // processor supports 256-bit vectors:
+                    if os_avx512_support && avx10_ebx & 0xff >= 1 {
+                        enable(avx10_ebx, 17, Feature::avx10_1_256);
+                        if !hybrid {
+                            enable(avx10_ebx, 18, Feature::avx10_1_512);
+                        }
+                    }
                 }
             }
         }
 
+        enable(extended_proc_info_ecx, 0, Feature::lahfsahf);
+
         // This detects ABM on AMD CPUs and LZCNT on Intel CPUs.
         // On intel CPUs with popcnt, lzcnt implements the
         // "missing part" of ABM, so we map both to the same
@@ -232,6 +546,8 @@ pub(crate) fn detect_features() -> cache::Initializer {
         // internally maps to Feature::abm.
         enable(extended_proc_info_ecx, 5, Feature::lzcnt);
 
+        enable(extended_proc_info_edx, 27, Feature::rdtscp);
+
         // As Hygon Dhyana originates from AMD technology and shares most of the architecture with
         // AMD's family 17h, but with different CPU Vendor ID("HygonGenuine")/Family series
         // number(Family 18h).
@@ -246,8 +562,2114 @@ pub(crate) fn detect_features() -> cache::Initializer {
             // These features are available on AMD arch CPUs:
             enable(extended_proc_info_ecx, 6, Feature::sse4a);
             enable(extended_proc_info_ecx, 21, Feature::tbm);
+
+            if extended_max_basic_leaf >= 0x8000_0007_u32 {
+                // "Advanced Power Management Information", EDX[9]: core
+                // performance boost.
+                enable(apm_edx, 9, Feature::cpb);
+            }
         }
     }
 
     value
 }
+
+/// Returns `true` if the current process is likely running under an
+/// emulator such as QEMU's TCG, rather than on real silicon or under a
+/// hardware-assisted hypervisor.
+///
+/// This is a best-effort heuristic: it requires the CPUID "hypervisor
+/// present" bit, and then either an emulator's hypervisor vendor ID or a
+/// combination of features that real processors never report (e.g. AVX2
+/// without AVX). Emulators that hide themselves are not detected, and a
+/// hypervisor that masks features inconsistently is reported as an emulator.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __likely_emulated() -> bool {
+    if !cpuid_usable() {
+        return false;
+    }
+    // See `detect_features`.
+    likely_emulated_with(
+        |leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) },
+        |xcr| unsafe { _xgetbv(xcr) },
+    )
+}
+
+/// Hypervisor vendor IDs (CPUID leaf 0x4000_0000 EBX, ECX, EDX) of
+/// emulators.
+const EMULATOR_VENDOR_IDS: &[&[u8; 12]] = &[
+    b"TCGTCGTCGTCG", // QEMU TCG
+];
+
+fn likely_emulated_with(
+    mut cpuid: impl FnMut(u32, u32) -> CpuidResult,
+    xgetbv: impl FnMut(u32) -> u64,
+) -> bool {
+    if cpuid(0, 0).eax < 1 {
+        return false;
+    }
+    // CPUID.1:ECX[bit 31] is reserved and always zero on real processors;
+    // hypervisors set it to signal their presence.
+    let hypervisor_present = bit::test(cpuid(1, 0).ecx as usize, 31);
+    if !hypervisor_present {
+        return false;
+    }
+
+    // EAX = 0x4000_0000: Hypervisor vendor ID, which is only defined if the
+    // hypervisor bit is set.
+    let CpuidResult { ebx, ecx, edx, .. } = cpuid(0x4000_0000, 0);
+    let mut vendor_id = [0_u8; 12];
+    vendor_id[0..4].copy_from_slice(&ebx.to_le_bytes());
+    vendor_id[4..8].copy_from_slice(&ecx.to_le_bytes());
+    vendor_id[8..12].copy_from_slice(&edx.to_le_bytes());
+    if EMULATOR_VENDOR_IDS.contains(&&vendor_id) {
+        return true;
+    }
+
+    // The features are decoded before implied features are expanded, so that
+    // a feature reported without the features it implies stands out:
+    let value = detect_features_with(cpuid, xgetbv);
+    super::IMPLIED_FEATURES
+        .iter()
+        .any(|&(f, implied)| value.test(f as u32) && !value.test(implied as u32))
+}
+
+/// Returns `true` if the `TPAUSE`, `UMONITOR`, and `UMWAIT` instructions are
+/// likely usable, i.e., if the processor reports `WAITPKG`
+/// (`CPUID.(EAX=07H,ECX=0):ECX[bit 5]`) and the process is not running under
+/// a hypervisor.
+///
+/// This is a best-effort heuristic: a hypervisor can intercept these
+/// instructions, or disable them in the guest, while still reporting
+/// `WAITPKG`, so the bit is not trusted if the CPUID "hypervisor present" bit
+/// is set. Hypervisors that hide themselves are not detected.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __tpause_likely_usable() -> bool {
+    if !cpuid_usable() {
+        return false;
+    }
+    // See `detect_features`.
+    tpause_likely_usable_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn tpause_likely_usable_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> bool {
+    if cpuid(0, 0).eax < 7 {
+        return false;
+    }
+    let hypervisor_present = bit::test(cpuid(1, 0).ecx as usize, 31);
+    !hypervisor_present && bit::test(cpuid(7, 0).ecx as usize, 5)
+}
+
+/// Returns the number of general-purpose performance-monitoring counters per
+/// logical processor, or `None` if the processor does not support
+/// architectural performance monitoring.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __num_pmc() -> Option<u8> {
+    if !cpuid_usable() {
+        return None;
+    }
+    // See `detect_features`.
+    num_pmc_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn num_pmc_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> Option<u8> {
+    if cpuid(0, 0).eax < 0xa {
+        return None;
+    }
+    // EAX = 0AH: Queries "Architectural Performance Monitoring Leaf";
+    // - EAX[7:0]: version ID, `0` if architectural performance monitoring is
+    // not supported,
+    // - EAX[15:8]: number of general-purpose counters per logical processor.
+    let CpuidResult { eax, .. } = cpuid(0xa, 0);
+    let version_id = eax & 0xff;
+    if version_id == 0 {
+        return None;
+    }
+    Some(((eax >> 8) & 0xff) as u8)
+}
+
+/// The AMD Secure Encrypted Virtualization (SEV) level reported by the
+/// processor, see [`__sev_status`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub enum SevStatus {
+    /// SEV is not supported, or the processor is not an AMD processor.
+    None,
+    /// SEV: the guest memory is encrypted.
+    Sev,
+    /// SEV-ES: the guest register state is also encrypted.
+    SevEs,
+    /// SEV-SNP: the guest memory is also integrity protected.
+    SevSnp,
+}
+
+/// Returns the highest AMD SEV level reported in CPUID leaf 0x8000_001F
+/// ("AMD Memory Encryption").
+///
+/// Inside an SEV guest the hypervisor reports the levels that are active for
+/// the guest; outside of a guest these are the levels the processor supports.
+/// The authoritative guest status is the `SEV_STATUS` MSR, which cannot be
+/// read from user space.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __sev_status() -> SevStatus {
+    if !cpuid_usable() {
+        return SevStatus::None;
+    }
+    // See `detect_features`.
+    sev_status_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn sev_status_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> SevStatus {
+    let CpuidResult { ebx, ecx, edx, .. } = cpuid(0, 0);
+    let vendor_id = [ebx.to_le_bytes(), edx.to_le_bytes(), ecx.to_le_bytes()];
+    if vendor_id != [*b"Auth", *b"enti", *b"cAMD"] && vendor_id != [*b"Hygo", *b"nGen", *b"uine"] {
+        return SevStatus::None;
+    }
+    if cpuid(0x8000_0000, 0).eax < 0x8000_001f {
+        return SevStatus::None;
+    }
+    // EAX = 0x8000_001F: Queries "AMD Memory Encryption";
+    // - EAX[1]: SEV, EAX[3]: SEV-ES, EAX[4]: SEV-SNP.
+    let CpuidResult { eax, .. } = cpuid(0x8000_001f, 0);
+    let has = |b| bit::test(eax as usize, b);
+    if !has(1) {
+        SevStatus::None
+    } else if has(3) && has(4) {
+        SevStatus::SevSnp
+    } else if has(3) {
+        SevStatus::SevEs
+    } else {
+        SevStatus::Sev
+    }
+}
+
+/// Returns `true` if the local APIC timer of the processor supports the
+/// TSC-deadline mode, i.e., `CPUID.01H:ECX.TSC_Deadline[bit 24]`.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __has_tsc_deadline() -> bool {
+    if !cpuid_usable() {
+        return false;
+    }
+    // See `detect_features`.
+    has_tsc_deadline_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn has_tsc_deadline_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> bool {
+    if cpuid(0, 0).eax < 1 {
+        return false;
+    }
+    bit::test(cpuid(1, 0).ecx as usize, 24)
+}
+
+/// Returns `true` if the processor supports the `IA32_TSC_ADJUST` MSR, i.e.,
+/// `CPUID.(EAX=07H,ECX=0):EBX.IA32_TSC_ADJUST[bit 1]`.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __has_tsc_adjust() -> bool {
+    if !cpuid_usable() {
+        return false;
+    }
+    // See `detect_features`.
+    has_tsc_adjust_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn has_tsc_adjust_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> bool {
+    if cpuid(0, 0).eax < 7 {
+        return false;
+    }
+    bit::test(cpuid(7, 0).ebx as usize, 1)
+}
+
+/// Returns `true` if 5-level paging, i.e., 57-bit linear addresses, is in use.
+///
+/// `CR4.LA57` is not readable from user space. The processor must support it,
+/// `CPUID.(EAX=07H,ECX=0):ECX.LA57[bit 16]`, with 57 linear-address bits in
+/// CPUID leaf 0x8000_0008, and on Linux the kernel clears the `la57` flag of
+/// `/proc/cpuinfo` if it has not enabled it. Elsewhere, or if that cannot be
+/// read, whether it is enabled is unknown and `false` is returned.
+///
+/// Even with 5-level paging, operating systems like Linux only map memory
+/// above the 47-bit boundary if the program explicitly asks for it, so code
+/// that tags the upper bits of pointers must not assume they are unused.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __la57_active() -> bool {
+    if !cpuid_usable() {
+        return false;
+    }
+    // See `detect_features`.
+    la57_active_with(
+        |leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) },
+        os_la57_enabled,
+    )
+}
+
+/// Returns whether the OS has enabled 5-level paging, or `None` if unknown.
+#[cfg(all(
+    target_os = "linux",
+    target_arch = "x86_64",
+    feature = "std_detect_file_io"
+))]
+fn os_la57_enabled() -> Option<bool> {
+    crate::detect::la57::la57_enabled()
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    target_arch = "x86_64",
+    feature = "std_detect_file_io"
+)))]
+fn os_la57_enabled() -> Option<bool> {
+    None
+}
+
+fn la57_active_with(
+    mut cpuid: impl FnMut(u32, u32) -> CpuidResult,
+    os_enabled: impl FnOnce() -> Option<bool>,
+) -> bool {
+    // CPUID.(EAX=07H,ECX=0):ECX.LA57[bit 16]:
+    if cpuid(0, 0).eax < 7 || !bit::test(cpuid(7, 0).ecx as usize, 16) {
+        return false;
+    }
+    if cpuid(0x8000_0000, 0).eax < 0x8000_0008 {
+        return false;
+    }
+    // EAX = 0x8000_0008: Queries "Virtual and Physical Address Sizes";
+    // - EAX[7:0]: physical-address bits, EAX[15:8]: linear-address bits.
+    let CpuidResult { eax, .. } = cpuid(0x8000_0008, 0);
+    (eax >> 8) & 0xff == 57 && os_enabled().unwrap_or(false)
+}
+
+/// The processor topology of the current logical processor reported in AMD's
+/// CPUID leaf 0x8000_001E ("Extended APIC ID"), see [`__amd_topology`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub struct AmdTopology {
+    /// The 32-bit extended APIC ID of the logical processor.
+    pub extended_apic_id: u32,
+    /// The ID of the core (compute unit on family 15h) within the node.
+    pub core_id: u8,
+    /// The number of threads per core (compute unit on family 15h).
+    pub threads_per_core: u8,
+    /// The ID of the node within the system.
+    pub node_id: u8,
+    /// The number of nodes per processor.
+    pub nodes_per_processor: u8,
+}
+
+/// Returns the [`AmdTopology`] of the logical processor that executes this
+/// function, or `None` if the processor is not an AMD or Hygon one or does not
+/// support topology extensions.
+///
+/// The thread may be migrated to another logical processor right after this
+/// returns, so the IDs are only a hint unless the thread is pinned.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __amd_topology() -> Option<AmdTopology> {
+    if !cpuid_usable() {
+        return None;
+    }
+    // See `detect_features`.
+    amd_topology_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn amd_topology_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> Option<AmdTopology> {
+    let CpuidResult { ebx, ecx, edx, .. } = cpuid(0, 0);
+    let vendor_id = [ebx.to_le_bytes(), edx.to_le_bytes(), ecx.to_le_bytes()];
+    if vendor_id != [*b"Auth", *b"enti", *b"cAMD"] && vendor_id != [*b"Hygo", *b"nGen", *b"uine"] {
+        return None;
+    }
+    if cpuid(0x8000_0000, 0).eax < 0x8000_001e {
+        return None;
+    }
+    // CPUID.8000_0001H:ECX[22]: TopologyExtensions.
+    if !bit::test(cpuid(0x8000_0001, 0).ecx as usize, 22) {
+        return None;
+    }
+    // EAX = 0x8000_001E: Queries "Extended APIC ID";
+    // - EAX: extended APIC ID,
+    // - EBX[7:0]: core ID, EBX[15:8]: threads per core minus one,
+    // - ECX[7:0]: node ID, ECX[10:8]: nodes per processor minus one.
+    let CpuidResult { eax, ebx, ecx, .. } = cpuid(0x8000_001e, 0);
+    Some(AmdTopology {
+        extended_apic_id: eax,
+        core_id: ebx as u8,
+        threads_per_core: ((ebx >> 8) & 0xff) as u8 + 1,
+        node_id: ecx as u8,
+        nodes_per_processor: ((ecx >> 8) & 0x7) as u8 + 1,
+    })
+}
+
+/// Returns `true` if the current process runs inside an Intel Trust Domain
+/// Extensions (TDX) guest.
+///
+/// TDX guests report the `IntelTDX    ` vendor signature in CPUID leaf 0x21.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __is_tdx_guest() -> bool {
+    if !cpuid_usable() {
+        return false;
+    }
+    // See `detect_features`.
+    is_tdx_guest_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn is_tdx_guest_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> bool {
+    if cpuid(0, 0).eax < 0x21 {
+        return false;
+    }
+    // EAX = 21H, ECX = 0: Queries the TDX guest vendor signature, which is
+    // stored in EBX, EDX, and ECX like the vendor ID of leaf 0.
+    let CpuidResult { ebx, ecx, edx, .. } = cpuid(0x21, 0);
+    [ebx.to_le_bytes(), edx.to_le_bytes(), ecx.to_le_bytes()] == [*b"Inte", *b"lTDX", *b"    "]
+}
+
+/// Returns the smallest and the largest monitor-line sizes in bytes of the
+/// `MONITOR` and `MWAIT` instructions, or `None` if the processor does not
+/// support them.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __monitor_line_sizes() -> Option<(u16, u16)> {
+    if !cpuid_usable() {
+        return None;
+    }
+    // See `detect_features`.
+    monitor_line_sizes_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn monitor_line_sizes_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> Option<(u16, u16)> {
+    if cpuid(0, 0).eax < 5 {
+        return None;
+    }
+    // CPUID.01H:ECX[3]: MONITOR/MWAIT.
+    if !bit::test(cpuid(1, 0).ecx as usize, 3) {
+        return None;
+    }
+    // EAX = 05H: Queries "MONITOR/MWAIT";
+    // - EAX[15:0]: smallest monitor-line size in bytes,
+    // - EBX[15:0]: largest monitor-line size in bytes.
+    let CpuidResult { eax, ebx, .. } = cpuid(5, 0);
+    Some(((eax & 0xffff) as u16, (ebx & 0xffff) as u16))
+}
+
+/// Returns `true` if the processor supports Intel Thread Director, i.e., the
+/// hardware feedback interface reports the performance and efficiency of each
+/// logical processor for several classes of workloads.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __has_thread_director() -> bool {
+    __thread_director_classes().is_some()
+}
+
+/// Returns the number of Intel Thread Director classes, or `None` if the
+/// processor does not support Intel Thread Director.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __thread_director_classes() -> Option<u8> {
+    if !cpuid_usable() {
+        return None;
+    }
+    // See `detect_features`.
+    thread_director_classes_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn thread_director_classes_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> Option<u8> {
+    if cpuid(0, 0).eax < 6 {
+        return None;
+    }
+    // EAX = 06H: Queries "Thermal and Power Management";
+    // - EAX[19]: hardware feedback interface, EAX[23]: Intel Thread Director,
+    // - ECX[15:8]: number of Intel Thread Director classes.
+    let CpuidResult { eax, ecx, .. } = cpuid(6, 0);
+    if !bit::test(eax as usize, 19) || !bit::test(eax as usize, 23) {
+        return None;
+    }
+    Some(((ecx >> 8) & 0xff) as u8)
+}
+
+/// Returns the version of AVX10 that the processor supports, e.g., `1` for
+/// AVX10.1, or `None` if it does not support AVX10.
+///
+/// This only reports what CPUID enumerates. Whether the OS has enabled the
+/// state that AVX10 instructions need is reflected by the `avx10.1-256` and
+/// `avx10.1-512` features.
+///
+/// On hybrid processors CPUID describes the core that executes it, so code
+/// that migrates between cores should not assume that every core reports the
+/// same, see [`__avx10_max_vlen_bits`].
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __avx10_version() -> Option<u8> {
+    if !cpuid_usable() {
+        return None;
+    }
+    // See `detect_features`.
+    avx10_version_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn avx10_version_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> Option<u8> {
+    if cpuid(0, 0).eax < 0x24 || cpuid(7, 0).eax < 1 {
+        return None;
+    }
+    // CPUID.(EAX=07H,ECX=01H):EDX[19]: AVX10.
+    if !bit::test(cpuid(7, 1).edx as usize, 19) {
+        return None;
+    }
+    // EAX = 24H, ECX = 0: EBX[7:0] is the AVX10 version.
+    match cpuid(0x24, 0).ebx & 0xff {
+        0 => None,
+        version => Some(version as u8),
+    }
+}
+
+/// Returns the largest AVX10 vector length in bits, i.e., `128`, `256`, or
+/// `512`, that CPUID leaf 0x24 reports, or `None` if the processor does not
+/// support AVX10.
+///
+/// On hybrid processors, e.g., with P-cores and E-cores, this is the vector
+/// length of the core that the query ran on, which can be wider than that of
+/// the other cores. Code must either pin its threads to the kind of core that
+/// it queried, or only rely on the narrower width that every core supports,
+/// which is what the `avx10.1-256` and `avx10.1-512` features report.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __avx10_max_vlen_bits() -> Option<u16> {
+    if !cpuid_usable() {
+        return None;
+    }
+    // See `detect_features`.
+    avx10_max_vlen_bits_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn avx10_max_vlen_bits_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> Option<u16> {
+    avx10_version_with(&mut cpuid)?;
+    // EAX = 24H, ECX = 0: EBX[16], EBX[17], and EBX[18] report support for
+    // 128-bit, 256-bit, and 512-bit vectors.
+    let ebx = cpuid(0x24, 0).ebx;
+    [(18, 512), (17, 256), (16, 128)]
+        .iter()
+        .find(|&&(bit, _)| bit::test(ebx as usize, bit))
+        .map(|&(_, bits)| bits)
+}
+
+/// The vendor ID, family, model, and stepping of the processor reported in
+/// CPUID leaves 0 and 1, see [`__cpu_signature`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub struct CpuSignature {
+    /// The vendor ID, e.g., `*b"GenuineIntel"` or `*b"AuthenticAMD"`.
+    pub vendor_id: [u8; 12],
+    /// The family, including the extended family, e.g., `0x6` for Intel Core
+    /// processors and `0x19` for AMD Zen 3 processors.
+    pub family: u16,
+    /// The model, including the extended model.
+    pub model: u8,
+    /// The stepping.
+    pub stepping: u8,
+}
+
+/// Returns the [`CpuSignature`] of the processor, or `None` if CPUID leaf 1
+/// is not supported.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __cpu_signature() -> Option<CpuSignature> {
+    if !cpuid_usable() {
+        return None;
+    }
+    // See `detect_features`.
+    cpu_signature_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+fn cpu_signature_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> Option<CpuSignature> {
+    let CpuidResult {
+        eax: max_basic_leaf,
+        ebx,
+        ecx,
+        edx,
+    } = cpuid(0, 0);
+    if max_basic_leaf < 1 {
+        return None;
+    }
+    let mut vendor_id = [0_u8; 12];
+    vendor_id[0..4].copy_from_slice(&ebx.to_le_bytes());
+    vendor_id[4..8].copy_from_slice(&edx.to_le_bytes());
+    vendor_id[8..12].copy_from_slice(&ecx.to_le_bytes());
+
+    // EAX = 1: EAX contains the version information;
+    // - EAX[3:0]: stepping, EAX[7:4]: model, EAX[11:8]: family,
+    // - EAX[19:16]: extended model, EAX[27:20]: extended family.
+    let CpuidResult { eax, .. } = cpuid(1, 0);
+    let family = (eax >> 8) & 0xf;
+    let model = (eax >> 4) & 0xf;
+    // The extended family only extends family 0xF, and the extended model
+    // only extends families 0x6 and 0xF:
+    let extended_family = if family == 0xf { (eax >> 20) & 0xff } else { 0 };
+    let extended_model = if family == 0x6 || family == 0xf {
+        (eax >> 12) & 0xf0
+    } else {
+        0
+    };
+    Some(CpuSignature {
+        vendor_id,
+        family: (family + extended_family) as u16,
+        model: (model | extended_model) as u8,
+        stepping: (eax & 0xf) as u8,
+    })
+}
+
+/// Returns `true` if the processor likely incurs a large penalty when
+/// switching between SSE instructions and 256-bit AVX instructions whose
+/// upper halves have not been cleared with `vzeroupper`.
+///
+/// This is a heuristic based on [`__cpu_signature`]: it is `true` for the
+/// Intel Sandy Bridge, Ivy Bridge, Haswell, and Broadwell microarchitectures,
+/// which save and restore the upper halves on each transition. Skylake and
+/// later Intel processors instead add a false dependency to every SSE
+/// instruction, and AMD processors do not have such a transition penalty; on
+/// those this returns `false`, even though `vzeroupper` is still beneficial.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __has_avx_sse_transition_penalty() -> bool {
+    match __cpu_signature() {
+        Some(signature) => has_avx_sse_transition_penalty(signature),
+        None => false,
+    }
+}
+
+/// The models of family 0x6 Intel processors with the AVX-SSE transition
+/// penalty.
+const AVX_SSE_TRANSITION_PENALTY_MODELS: &[u8] = &[
+    0x2a, 0x2d, // Sandy Bridge
+    0x3a, 0x3e, // Ivy Bridge
+    0x3c, 0x3f, 0x45, 0x46, // Haswell
+    0x3d, 0x47, 0x4f, 0x56, // Broadwell
+];
+
+fn has_avx_sse_transition_penalty(signature: CpuSignature) -> bool {
+    signature.vendor_id == *b"GenuineIntel"
+        && signature.family == 0x6
+        && AVX_SSE_TRANSITION_PENALTY_MODELS.contains(&signature.model)
+}
+
+/// Returns `true` if the processor supports `rdrand` or `rdseed`, and is
+/// not known to have errata that make these instructions return bad values.
+///
+/// This is advisory: it only checks [`__cpu_signature`] against a list of
+/// processors with documented RNG errata, some of which are fixed by
+/// microcode or firmware updates that this function cannot detect, and it
+/// does not test the output of the instructions.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __hwrng_trustworthy() -> bool {
+    if !cpuid_usable() {
+        return false;
+    }
+    // See `detect_features`.
+    hwrng_trustworthy_with(|leaf, sub_leaf| unsafe { __cpuid_count(leaf, sub_leaf) })
+}
+
+/// The families, and the ranges of models, of AMD processors with
+/// documented RNG errata.
+const RNG_ERRATA_AMD_MODELS: &[(u16, u8, u8)] = &[
+    // `rdrand` always returns `!0` after a suspend and resume cycle:
+    (0x15, 0x00, 0xff), // Bulldozer to Excavator
+    (0x16, 0x00, 0xff), // Jaguar and Puma
+    // `rdrand` always returns `!0` with early firmware:
+    (0x17, 0x71, 0x71), // Zen 2 (Matisse)
+    // The 16-bit and 32-bit forms of `rdseed` can return `0` as a valid
+    // random number:
+    (0x1a, 0x00, 0xff), // Zen 5
+];
+
+fn hwrng_trustworthy_with(mut cpuid: impl FnMut(u32, u32) -> CpuidResult) -> bool {
+    let signature = match cpu_signature_with(&mut cpuid) {
+        Some(signature) => signature,
+        None => return false,
+    };
+    let rdrand = bit::test(cpuid(1, 0).ecx as usize, 30);
+    let rdseed = cpuid(0, 0).eax >= 7 && bit::test(cpuid(7, 0).ebx as usize, 18);
+    (rdrand || rdseed) && !has_rng_errata(signature)
+}
+
+fn has_rng_errata(signature: CpuSignature) -> bool {
+    signature.vendor_id == *b"AuthenticAMD"
+        && RNG_ERRATA_AMD_MODELS.iter().any(|&(family, first, last)| {
+            signature.family == family && first <= signature.model && signature.model <= last
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::vec::Vec;
+
+    /// Runs `detect_features_with` against a fake CPU that reports every leaf
+    /// and every feature bit, recording the `cpuid` calls.
+    fn cpuid_calls() -> Vec<(u32, u32)> {
+        let calls = RefCell::new(Vec::new());
+        detect_features_with(
+            |leaf, sub_leaf| {
+                calls.borrow_mut().push((leaf, sub_leaf));
+                let eax = match (leaf, sub_leaf) {
+                    (0, _) => 0xd,
+                    (7, 0) => 1,
+                    (0x8000_0000, _) => 0x8000_0007,
+                    _ => !0,
+                };
+                CpuidResult {
+                    eax,
+                    ebx: !0,
+                    ecx: !0,
+                    edx: !0,
+                }
+            },
+            |_| !0,
+        );
+        calls.into_inner()
+    }
+
+    #[test]
+    fn cpuid_leaves_are_queried_once() {
+        let calls = cpuid_calls();
+        for (i, call) in calls.iter().enumerate() {
+            assert!(!calls[..i].contains(call), "{:x?} queried twice", call);
+        }
+        assert_eq!(
+            calls,
+            [
+                (0, 0),
+                (1, 0),
+                (7, 0),
+                (7, 1),
+                (0xd, 0),
+                (0xd, 1),
+                (0x8000_0000, 0),
+                (0x8000_0001, 0),
+                (0x8000_0007, 0),
+            ]
+        );
+    }
+
+    /// The most `cpuid` calls that `detect_features` may issue: leaves 0, 1,
+    /// 7.0, 7.1, 0x24, 0xd.0, 0xd.1, 0x8000_0000, 0x8000_0001, and
+    /// 0x8000_0007. Reading another leaf requires raising this bound.
+    const MAX_CPUID_CALLS: usize = 10;
+
+    /// The most `xgetbv` calls that `detect_features` may issue: `XCR0`.
+    const MAX_XGETBV_CALLS: usize = 1;
+
+    /// Runs `detect_features_with` against a fake CPU whose highest basic and
+    /// extended leaves are `max_basic_leaf` and `extended_max_basic_leaf`, and
+    /// that sets every other bit, returning the number of `cpuid` and
+    /// `xgetbv` calls.
+    fn detection_cost(max_basic_leaf: u32, extended_max_basic_leaf: u32) -> (usize, usize) {
+        let cpuid_calls = RefCell::new(0);
+        let xgetbv_calls = RefCell::new(0);
+        detect_features_with(
+            |leaf, _| {
+                *cpuid_calls.borrow_mut() += 1;
+                let eax = match leaf {
+                    0 => max_basic_leaf,
+                    0x8000_0000 => extended_max_basic_leaf,
+                    _ => !0,
+                };
+                CpuidResult {
+                    eax,
+                    ebx: !0,
+                    ecx: !0,
+                    edx: !0,
+                }
+            },
+            |_| {
+                *xgetbv_calls.borrow_mut() += 1;
+                !0
+            },
+        );
+        (cpuid_calls.into_inner(), xgetbv_calls.into_inner())
+    }
+
+    #[test]
+    fn detection_cost_is_bounded() {
+        let mut max_cpuid_calls = 0;
+        for &max_basic_leaf in &[0, 1, 6, 7, 0xd, 0x23, 0x24, 0xff, !0] {
+            for &extended_max_basic_leaf in &[0, 0x8000_0001, 0x8000_0007, 0x8000_0026, !0] {
+                let (cpuid, xgetbv) = detection_cost(max_basic_leaf, extended_max_basic_leaf);
+                assert!(
+                    cpuid <= MAX_CPUID_CALLS,
+                    "{} cpuid calls for leaves {:#x} and {:#x}",
+                    cpuid,
+                    max_basic_leaf,
+                    extended_max_basic_leaf
+                );
+                assert!(xgetbv <= MAX_XGETBV_CALLS, "{} xgetbv calls", xgetbv);
+                max_cpuid_calls = max_cpuid_calls.max(cpuid);
+            }
+        }
+        // The bound is tight, so that it is raised deliberately rather than
+        // drifting:
+        assert_eq!(max_cpuid_calls, MAX_CPUID_CALLS);
+        // A CPU without any leaf beyond 0 only costs a single call:
+        assert_eq!(detection_cost(0, 0), (1, 0));
+    }
+
+    /// Asserts that decoding `leaves` enables the features in `enabled`, and
+    /// not those in `disabled`.
+    fn assert_decoded(leaves: &CpuidLeaves, enabled: &[Feature], disabled: &[Feature]) {
+        let value = decode_x86_features(leaves);
+        for &f in enabled {
+            assert!(value.test(f as u32), "{} not enabled", f.to_str());
+        }
+        for &f in disabled {
+            assert!(!value.test(f as u32), "{} enabled", f.to_str());
+        }
+    }
+
+    #[test]
+    fn decode_core_2_duo_e8400() {
+        // Penryn, whose OS has not set `osxsave`:
+        let leaves = CpuidLeaves {
+            max_basic_leaf: 0xd,
+            vendor_id: *b"GenuineIntel",
+            proc_info_ecx: 0x0408_e3fd,
+            proc_info_edx: 0xbfeb_fbff,
+            extended_max_basic_leaf: 0x8000_0008,
+            extended_proc_info_ecx: 0x1,
+            extended_proc_info_edx: 0x2010_0800,
+            ..CpuidLeaves::default()
+        };
+        assert_decoded(
+            &leaves,
+            &[
+                Feature::x87,
+                Feature::tsc,
+                Feature::mmx,
+                Feature::fxsr,
+                Feature::sse,
+                Feature::sse2,
+                Feature::sse3,
+                Feature::ssse3,
+                Feature::sse4_1,
+                Feature::cmpxchg16b,
+                Feature::lahfsahf,
+            ],
+            &[
+                Feature::sse4_2,
+                Feature::popcnt,
+                Feature::xsave,
+                Feature::avx,
+                Feature::lzcnt,
+                Feature::rdtscp,
+            ],
+        );
+    }
+
+    #[test]
+    fn decode_core_i7_4770() {
+        // Haswell, without TSX:
+        let leaves = CpuidLeaves {
+            max_basic_leaf: 0xd,
+            vendor_id: *b"GenuineIntel",
+            proc_info_ecx: 0x7ffa_fbff,
+            proc_info_edx: 0xbfeb_fbff,
+            extended_features_ebx: 0x27ab,
+            extended_features_edx: 0x9c00_0000,
+            proc_extended_state_components: 0x7,
+            proc_extended_state1_eax: 0x1,
+            xcr0: 0x7,
+            extended_max_basic_leaf: 0x8000_0008,
+            extended_proc_info_ecx: 0x21,
+            extended_proc_info_edx: 0x2c10_0800,
+            ..CpuidLeaves::default()
+        };
+        assert_decoded(
+            &leaves,
+            &[
+                Feature::aes,
+                Feature::pclmulqdq,
+                Feature::popcnt,
+                Feature::movbe,
+                Feature::rdrand,
+                Feature::xsave,
+                Feature::xsaveopt,
+                Feature::avx,
+                Feature::avx2,
+                Feature::fma,
+                Feature::f16c,
+                Feature::bmi1,
+                Feature::bmi2,
+                Feature::lzcnt,
+                Feature::rdtscp,
+            ],
+            &[
+                Feature::rtm,
+                Feature::adx,
+                Feature::rdseed,
+                Feature::xsavec,
+                Feature::sha,
+                Feature::avx512f,
+                Feature::sse4a,
+            ],
+        );
+    }
+
+    #[test]
+    fn decode_core_i7_1065g7() {
+        // Ice Lake, with AVX-512:
+        let leaves = CpuidLeaves {
+            max_basic_leaf: 0x1b,
+            vendor_id: *b"GenuineIntel",
+            proc_info_ecx: 0x7ffa_fbff,
+            proc_info_edx: 0xbfeb_fbff,
+            extended_features_ebx: 0xf2bf_27ef,
+            extended_features_ecx: 0x4040_5f5e,
+            extended_features_edx: 0xbc00_0410,
+            proc_extended_state_components: 0x2e7,
+            proc_extended_state1_eax: 0xf,
+            xcr0: 0x2e7,
+            extended_max_basic_leaf: 0x8000_0008,
+            extended_proc_info_ecx: 0x121,
+            extended_proc_info_edx: 0x2c10_0800,
+            ..CpuidLeaves::default()
+        };
+        let avx512 = [
+            Feature::avx512f,
+            Feature::avx512cd,
+            Feature::avx512bw,
+            Feature::avx512dq,
+            Feature::avx512vl,
+            Feature::avx512ifma,
+            Feature::avx512vbmi,
+            Feature::avx512vbmi2,
+            Feature::avx512vnni,
+            Feature::avx512bitalg,
+            Feature::avx512vpopcntdq,
+            Feature::avx512gfni,
+            Feature::avx512vaes,
+        ];
+        let others = [
+            Feature::avx512bf16,
+            Feature::avx512vp2intersect,
+            Feature::avx512er,
+            Feature::avx512pf,
+            Feature::sse4a,
+        ];
+        assert_decoded(&leaves, &avx512, &others);
+        assert_decoded(
+            &leaves,
+            &[Feature::sha, Feature::gfni, Feature::vaes, Feature::xsaves],
+            &[],
+        );
+
+        // The OS does not enable the AVX-512 state:
+        let leaves = CpuidLeaves {
+            xcr0: 0x7,
+            ..leaves
+        };
+        assert_decoded(&leaves, &[Feature::avx2, Feature::gfni], &avx512);
+    }
+
+    #[test]
+    fn decode_ryzen_9_5950x() {
+        // Zen 3:
+        let leaves = CpuidLeaves {
+            max_basic_leaf: 0x10,
+            vendor_id: *b"AuthenticAMD",
+            proc_info_ecx: 0x7ef8_320b,
+            proc_info_edx: 0x178b_fbff,
+            extended_features_ebx: 0x219c_97a9,
+            extended_features_ecx: 0x0040_069c,
+            proc_extended_state_components: 0x207,
+            proc_extended_state1_eax: 0xf,
+            xcr0: 0x207,
+            extended_max_basic_leaf: 0x8000_0023,
+            extended_proc_info_ecx: 0x75c2_37ff,
+            extended_proc_info_edx: 0x2fd3_fbff,
+            apm_edx: 0x6799,
+            ..CpuidLeaves::default()
+        };
+        assert_decoded(
+            &leaves,
+            &[
+                Feature::avx2,
+                Feature::sha,
+                Feature::adx,
+                Feature::rdseed,
+                Feature::clflushopt,
+                Feature::clwb,
+                Feature::vaes,
+                Feature::vpclmulqdq,
+                Feature::xsaves,
+                Feature::lzcnt,
+                Feature::sse4a,
+                Feature::rdtscp,
+                Feature::cpb,
+            ],
+            &[Feature::gfni, Feature::avx512f, Feature::tbm, Feature::rtm],
+        );
+
+        // The same leaves from an Intel CPU do not report the AMD features:
+        let leaves = CpuidLeaves {
+            vendor_id: *b"GenuineIntel",
+            ..leaves
+        };
+        assert_decoded(&leaves, &[Feature::lzcnt], &[Feature::sse4a, Feature::cpb]);
+    }
+
+    #[test]
+    fn decode_read_leaves() {
+        // Decoding the leaves read from a CPUID dump is the same as detecting
+        // the features from it:
+        let leaves = [
+            (0, 0, [0xd, 0x756e_6547, 0x6c65_746e, 0x4965_6e69]),
+            (1, 0, [0x0003_06c3, 0, 0x7ffa_fbff, 0xbfeb_fbff]),
+            (7, 0, [0, 0x27ab, 0, 0x9c00_0000]),
+            (0xd, 0, [0x7, 0, 0, 0]),
+            (0xd, 1, [0x1, 0, 0, 0]),
+            (0x8000_0000, 0, [0x8000_0008, 0, 0, 0]),
+            (0x8000_0001, 0, [0, 0, 0x21, 0x2c10_0800]),
+        ];
+        let read = read_cpuid_leaves(fake_cpuid(&leaves), |_| 0x7);
+        assert_eq!(read.vendor_id, *b"GenuineIntel");
+        assert_eq!(read.xcr0, 0x7);
+        assert!(decode_x86_features(&read) == detect_features_from_dump(&leaves, 0x7));
+        assert!(decode_x86_features(&read) != cache::Initializer::default());
+    }
+
+    /// Runs `detect_features_with` against a fake CPU with AVX and AVX-512F
+    /// whose OS enables every state component in `XCR0`, but which only
+    /// enumerates `xsave_components` in CPUID leaf 0xD.
+    fn detect_with_xsave_components(xsave_components: u32) -> cache::Initializer {
+        detect_with_xstate(xsave_components, !0)
+    }
+
+    /// Like `detect_with_xsave_components`, but the OS only enables `xcr0`.
+    fn detect_with_xstate(xsave_components: u32, xcr0: u64) -> cache::Initializer {
+        detect_features_with(
+            |leaf, sub_leaf| {
+                let mut r = CpuidResult {
+                    eax: 0,
+                    ebx: 0,
+                    ecx: 0,
+                    edx: 0,
+                };
+                match (leaf, sub_leaf) {
+                    (0, _) => r.eax = 0xd,
+                    // xsave, osxsave, and avx:
+                    (1, _) => r.ecx = (1 << 26) | (1 << 27) | (1 << 28),
+                    // avx2 and avx512f:
+                    (7, 0) => r.ebx = (1 << 5) | (1 << 16),
+                    (0xd, 0) => r.eax = xsave_components,
+                    _ => {}
+                }
+                r
+            },
+            |xcr| {
+                assert_eq!(xcr, 0);
+                xcr0
+            },
+        )
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86")]
+    fn without_cpuid() {
+        // Any CPU that can run the test suite has CPUID, so only check that
+        // detection agrees with the EFLAGS probe.
+        let value = detect_features();
+        if has_cpuid() {
+            assert!(value.test(Feature::tsc as u32));
+        } else {
+            for bit in 0..Feature::_last as u32 {
+                assert!(!value.test(bit));
+            }
+            assert!(!__likely_emulated());
+        }
+    }
+
+    #[test]
+    fn legacy_baseline() {
+        // The features that are only guaranteed by the `x86_64` baseline are
+        // detected from CPUID.1:EDX, not assumed, because `i586` targets do
+        // not have them:
+        let features = [
+            (23, Feature::mmx),
+            (24, Feature::fxsr),
+            (25, Feature::sse),
+            (26, Feature::sse2),
+        ];
+        let detect = |edx: u32| {
+            let leaves = [(0, 0, [1, 0, 0, 0]), (1, 0, [0, 0, 0, edx])];
+            detect_features_with(fake_cpuid(&leaves), |_| !0)
+        };
+        let value = detect(0);
+        for &(_, f) in &features {
+            assert!(!value.test(f as u32), "{} detected", f.to_str());
+        }
+        for &(bit, f) in &features {
+            let value = detect(1 << bit);
+            for &(_, g) in &features {
+                assert_eq!(value.test(g as u32), g as u8 == f as u8, "{}", g.to_str());
+            }
+        }
+    }
+
+    #[test]
+    fn popcnt_lzcnt() {
+        let detect = |leaf1_ecx: u32, max_extended_leaf: u32, ext_ecx: u32| {
+            let leaves = [
+                (0, 0, [1, 0, 0, 0]),
+                (1, 0, [0, 0, leaf1_ecx, 0]),
+                (0x8000_0000, 0, [max_extended_leaf, 0, 0, 0]),
+                (0x8000_0001, 0, [0, 0, ext_ecx, 0]),
+            ];
+            let value = detect_features_with(fake_cpuid(&leaves), |_| !0);
+            (
+                value.test(Feature::popcnt as u32),
+                value.test(Feature::lzcnt as u32),
+            )
+        };
+        // POPCNT is CPUID.1:ECX[23], LZCNT/ABM is CPUID.8000_0001:ECX[5].
+        // Intel Nehalem has POPCNT but not LZCNT:
+        assert_eq!(detect(1 << 23, 0x8000_0008, 0), (true, false));
+        assert_eq!(detect(0, 0x8000_0008, 1 << 5), (false, true));
+        assert_eq!(detect(1 << 23, 0x8000_0008, 1 << 5), (true, true));
+        // The extended leaf is only read if it is supported:
+        assert_eq!(detect(1 << 23, 0x8000_0000, 1 << 5), (true, false));
+        assert_eq!(detect(1 << 23, 0, 1 << 5), (true, false));
+    }
+
+    #[test]
+    fn rdtscp() {
+        let detect = |max_extended_leaf: u32, ext_edx: u32| {
+            let leaves = [
+                (0, 0, [1, 0, 0, 0]),
+                (1, 0, [0, 0, 0, 1 << 4]),
+                (0x8000_0000, 0, [max_extended_leaf, 0, 0, 0]),
+                (0x8000_0001, 0, [0, 0, 0, ext_edx]),
+            ];
+            let value = detect_features_with(fake_cpuid(&leaves), |_| !0);
+            (
+                value.test(Feature::tsc as u32),
+                value.test(Feature::rdtscp as u32),
+            )
+        };
+        // RDTSCP is CPUID.8000_0001:EDX[27], e.g. on Skylake:
+        assert_eq!(detect(0x8000_0008, 0x2c10_0800), (true, true));
+        assert_eq!(detect(0x8000_0008, !(1 << 27)), (true, false));
+        // The extended leaf is only read if it is supported:
+        assert_eq!(detect(0x8000_0000, 1 << 27), (true, false));
+        assert_eq!(detect(0, 1 << 27), (true, false));
+    }
+
+    #[test]
+    fn sse4a() {
+        let detect = |vendor_id: &[u8; 12], max_extended_leaf: u32, ext_ecx: u32| {
+            let [leaf0, leaf1] = signature_leaves(vendor_id, 0x0010_0f42);
+            let leaves = [
+                leaf0,
+                leaf1,
+                (0x8000_0000, 0, [max_extended_leaf, 0, 0, 0]),
+                (0x8000_0001, 0, [0, 0, ext_ecx, 0]),
+            ];
+            detect_features_with(fake_cpuid(&leaves), |_| !0).test(Feature::sse4a as u32)
+        };
+        // SSE4a is CPUID.8000_0001:ECX[6], e.g. on Phenom II:
+        assert!(detect(b"AuthenticAMD", 0x8000_001b, 0x0000_37ff));
+        assert!(detect(b"HygonGenuine", 0x8000_001f, 1 << 6));
+        assert!(!detect(b"AuthenticAMD", 0x8000_001b, !(1 << 6)));
+        // The extended leaf is only read if it is supported:
+        assert!(!detect(b"AuthenticAMD", 0x8000_0000, 1 << 6));
+        // The bit is reserved on other processors:
+        assert!(!detect(b"GenuineIntel", 0x8000_0008, 1 << 6));
+    }
+
+    #[test]
+    fn cpb() {
+        let detect = |vendor_id: &[u8; 12], max_extended_leaf: u32, edx: u32| {
+            let [leaf0, leaf1] = signature_leaves(vendor_id, 0x0087_0f10);
+            let leaves = [
+                leaf0,
+                leaf1,
+                (0x8000_0000, 0, [max_extended_leaf, 0, 0, 0]),
+                (0x8000_0007, 0, [0, 0, 0, edx]),
+            ];
+            detect_features_with(fake_cpuid(&leaves), |_| !0).test(Feature::cpb as u32)
+        };
+        // Ryzen 7 3700X: TS, TTP, TM, HTC, 100MHzSteps, HwPstate, TscInvariant,
+        // CPB, EffFreqRO, ...
+        assert!(detect(b"AuthenticAMD", 0x8000_001f, 0x0000_6799));
+        assert!(detect(b"HygonGenuine", 0x8000_001f, 1 << 9));
+        assert!(!detect(b"AuthenticAMD", 0x8000_001f, !(1 << 9)));
+        // The leaf is not supported:
+        assert!(!detect(b"AuthenticAMD", 0x8000_0006, 1 << 9));
+        // Intel processors report the invariant TSC but no CPB in this leaf:
+        assert!(!detect(b"GenuineIntel", 0x8000_0008, 1 << 9));
+    }
+
+    #[test]
+    fn clflushopt_clwb() {
+        let detect = |ebx: u32| {
+            let leaves = [(0, 0, [7, 0, 0, 0]), (7, 0, [0, ebx, 0, 0])];
+            let value = detect_features_with(fake_cpuid(&leaves), |_| !0);
+            (
+                value.test(Feature::clflushopt as u32),
+                value.test(Feature::clwb as u32),
+            )
+        };
+        // Skylake client processors have CLFLUSHOPT but not CLWB:
+        assert_eq!(detect(0x029c_6fbf), (true, false));
+        // Cascade Lake has both:
+        assert_eq!(detect(0xd39f_fffb), (true, true));
+        assert_eq!(detect(1 << 24), (false, true));
+        assert_eq!(detect(!((1 << 23) | (1 << 24))), (false, false));
+    }
+
+    #[test]
+    fn movrs() {
+        // MOVRS is CPUID.(EAX=07H,ECX=01H):EAX.MOVRS[31]:
+        let detect = |eax1: u32, edx1: u32| {
+            let leaves = [
+                (0, 0, [7, 0, 0, 0]),
+                (7, 0, [1, 0, 0, 0]),
+                (7, 1, [eax1, 0, 0, edx1]),
+            ];
+            detect_features_with(fake_cpuid(&leaves), |_| !0).test(Feature::movrs as u32)
+        };
+        assert!(detect(1 << 31, 0));
+        assert!(!detect(!(1 << 31), !0));
+        // EDX[18] is CET_SSS, not MOVRS:
+        assert!(!detect(0, 1 << 18));
+        // Sub-leaf 1 is only read if sub-leaf 0 reports it:
+        let leaves = [
+            (0, 0, [7, 0, 0, 0]),
+            (7, 0, [0, 0, 0, 0]),
+            (7, 1, [1 << 31, 0, 0, 0]),
+        ];
+        let value = detect_features_with(fake_cpuid(&leaves), |_| !0);
+        assert!(!value.test(Feature::movrs as u32));
+    }
+
+    #[test]
+    fn x87() {
+        // The x87 FPU is CPUID.1:EDX.FPU[0], which can be missing on
+        // embedded processors:
+        let detect = |edx: u32| {
+            let leaves = [(0, 0, [1, 0, 0, 0]), (1, 0, [0, 0, 0, edx])];
+            detect_features_with(fake_cpuid(&leaves), |_| !0).test(Feature::x87 as u32)
+        };
+        assert!(detect(0xbfeb_fbff));
+        assert!(detect(1));
+        assert!(!detect(0));
+        assert!(!detect(!1));
+    }
+
+    #[test]
+    fn cmpxchg16b() {
+        // Early AMD64 processors lack CMPXCHG16B, CPUID.1:ECX.CX16[13]:
+        let detect = |ecx: u32| {
+            let leaves = [(0, 0, [1, 0, 0, 0]), (1, 0, [0, 0, ecx, 0])];
+            detect_features_with(fake_cpuid(&leaves), |_| !0).test(Feature::cmpxchg16b as u32)
+        };
+        assert!(!detect(0));
+        assert!(!detect(!(1 << 13)));
+        assert!(detect(1 << 13));
+    }
+
+    #[test]
+    fn xeon_phi() {
+        let features = [
+            (1, 26, Feature::avx512pf),
+            (1, 27, Feature::avx512er),
+            (3, 2, Feature::avx5124vnniw),
+            (3, 3, Feature::avx5124fmaps),
+        ];
+        for &(reg, bit, f) in &features {
+            let detect = |xsave_components: u32| {
+                // avx512f, and the feature in CPUID.7.0 EBX or EDX:
+                let mut leaf7 = [0, 1 << 16, 0, 0];
+                leaf7[reg] |= 1 << bit;
+                let leaves = [
+                    (0, 0, [0xd, 0, 0, 0]),
+                    // xsave, osxsave, and avx:
+                    (1, 0, [0, 0, (1 << 26) | (1 << 27) | (1 << 28), 0]),
+                    (7, 0, leaf7),
+                    (0xd, 0, [xsave_components, 0, 0, 0]),
+                ];
+                detect_features_with(fake_cpuid(&leaves), |_| !0).test(f as u32)
+            };
+            assert!(detect(0b1110_0111), "{} not detected", f.to_str());
+            // Without the AVX-512 state:
+            assert!(!detect(0b0000_0111), "{} detected", f.to_str());
+        }
+    }
+
+    #[test]
+    fn osxsave_not_set() {
+        let leaves = [
+            (0, 0, [0xd, 0, 0, 0]),
+            // xsave and avx, but not osxsave:
+            (1, 0, [0, 0, (1 << 26) | (1 << 28), 0]),
+            // avx2 and avx512f:
+            (7, 0, [0, (1 << 5) | (1 << 16), 0, 0]),
+            (0xd, 0, [0b1110_0111, 0, 0, 0]),
+        ];
+        // `xgetbv` raises #UD if the OS has not set `osxsave`:
+        let value = detect_features_with(fake_cpuid(&leaves), |_| panic!("xgetbv called"));
+        for &f in &[
+            Feature::osxsave,
+            Feature::xsave,
+            Feature::avx,
+            Feature::avx2,
+            Feature::avx512f,
+        ] {
+            assert!(!value.test(f as u32), "{} detected", f.to_str());
+        }
+
+        let value = detect_with_xsave_components(0b1110_0111);
+        assert!(value.test(Feature::osxsave as u32));
+    }
+
+    #[test]
+    fn vex_gfni_vaes_vpclmulqdq() {
+        let leaves = |xsave_components: u32| {
+            [
+                (0, 0, [0xd, 0, 0, 0]),
+                // pclmulqdq, xsave, osxsave, avx, and aes:
+                (
+                    1,
+                    0,
+                    [
+                        0,
+                        0,
+                        (1 << 1) | (1 << 25) | (1 << 26) | (1 << 27) | (1 << 28),
+                        0,
+                    ],
+                ),
+                // avx2; gfni, vaes, and vpclmulqdq:
+                (7, 0, [0, 1 << 5, (1 << 8) | (1 << 9) | (1 << 10), 0]),
+                (0xd, 0, [xsave_components, 0, 0, 0]),
+            ]
+        };
+        let features = [Feature::gfni, Feature::vaes, Feature::vpclmulqdq];
+
+        // AVX, but no AVX-512 state:
+        let value = detect_features_with(fake_cpuid(&leaves(0b111)), |_| !0);
+        for &f in &features {
+            assert!(value.test(f as u32), "{} not detected", f.to_str());
+        }
+        assert!(!value.test(Feature::avx512f as u32));
+        assert!(!value.test(Feature::avx512gfni as u32));
+        assert!(!value.test(Feature::avx512vaes as u32));
+
+        // No AVX state, only the SSE encodings of GFNI are usable:
+        let value = detect_features_with(fake_cpuid(&leaves(0b011)), |_| !0);
+        assert!(value.test(Feature::gfni as u32));
+        assert!(!value.test(Feature::avx as u32));
+        assert!(!value.test(Feature::vaes as u32));
+        assert!(!value.test(Feature::vpclmulqdq as u32));
+
+        // Tremont: GFNI without XSAVE or AVX at all:
+        let tremont = [
+            (0, 0, [0x1b, 0, 0, 0]),
+            // sse4.2, pclmulqdq, and aes:
+            (1, 0, [0, 0, (1 << 1) | (1 << 20) | (1 << 25), 1 << 26]),
+            // gfni:
+            (7, 0, [0, 0, 1 << 8, 0]),
+        ];
+        let value = detect_features_with(fake_cpuid(&tremont), |_| !0);
+        assert!(value.test(Feature::gfni as u32));
+        assert!(value.test(Feature::sse2 as u32));
+        assert!(!value.test(Feature::avx as u32));
+        assert!(!value.test(Feature::vaes as u32));
+    }
+
+    #[test]
+    fn xsave_components_enumerated() {
+        let value = detect_with_xsave_components(0b1110_0111);
+        assert!(value.test(Feature::avx as u32));
+        assert!(value.test(Feature::avx2 as u32));
+        assert!(value.test(Feature::avx512f as u32));
+    }
+
+    #[test]
+    fn xsave_avx512_components_not_enumerated() {
+        let value = detect_with_xsave_components(0b0000_0111);
+        assert!(value.test(Feature::avx as u32));
+        assert!(value.test(Feature::avx2 as u32));
+        assert!(!value.test(Feature::avx512f as u32));
+    }
+
+    #[test]
+    fn xsave_avx_component_not_enumerated() {
+        let value = detect_with_xsave_components(0b1110_0011);
+        assert!(!value.test(Feature::xsave as u32));
+        assert!(!value.test(Feature::avx as u32));
+        assert!(!value.test(Feature::avx2 as u32));
+        assert!(!value.test(Feature::avx512f as u32));
+    }
+
+    #[test]
+    fn movbe_bmi_without_avx() {
+        // MOVBE, BMI1, and BMI2 only use general-purpose registers, so they do
+        // not depend on the AVX state being enabled by the OS.
+        let detect = |leaf1_ecx: u32, xcr0: u64| {
+            let leaves = [
+                (0, 0, [0xd, 0, 0, 0]),
+                (1, 0, [0, 0, leaf1_ecx, 0]),
+                // bmi1, avx2, and bmi2:
+                (7, 0, [0, (1 << 3) | (1 << 5) | (1 << 8), 0, 0]),
+                (0xd, 0, [0b111, 0, 0, 0]),
+            ];
+            let value = detect_features_with(fake_cpuid(&leaves), |_| xcr0);
+            (
+                value.test(Feature::movbe as u32),
+                value.test(Feature::bmi1 as u32),
+                value.test(Feature::bmi2 as u32),
+                value.test(Feature::avx as u32),
+            )
+        };
+        // movbe, xsave, osxsave, and avx:
+        let leaf1_ecx = (1 << 22) | (1 << 26) | (1 << 27) | (1 << 28);
+        assert_eq!(detect(leaf1_ecx, 0b111), (true, true, true, true));
+        // The OS does not enable the AVX state:
+        assert_eq!(detect(leaf1_ecx, 0b011), (true, true, true, false));
+        // The OS does not use XSAVE at all:
+        assert_eq!(detect(leaf1_ecx & !(1 << 27), 0), (true, true, true, false));
+        // The processor does not support AVX:
+        assert_eq!(detect(1 << 22, 0), (true, true, true, false));
+    }
+
+    #[test]
+    fn f16c_requires_avx_state() {
+        // F16C converts to and from YMM registers, so it is only usable if the
+        // OS enables the AVX state like AVX itself.
+        let detect = |leaf1_ecx: u32, xsave_components: u32, xcr0: u64| {
+            let leaves = [
+                (0, 0, [0xd, 0, 0, 0]),
+                (1, 0, [0, 0, leaf1_ecx, 0]),
+                (0xd, 0, [xsave_components, 0, 0, 0]),
+            ];
+            let value = detect_features_with(fake_cpuid(&leaves), |_| xcr0);
+            (
+                value.test(Feature::avx as u32),
+                value.test(Feature::f16c as u32),
+            )
+        };
+        // xsave, osxsave, avx, and f16c, e.g. Ivy Bridge:
+        let ivb = (1 << 26) | (1 << 27) | (1 << 28) | (1 << 29);
+        assert_eq!(detect(ivb, 0b111, 0b111), (true, true));
+        // The OS does not enable the AVX state:
+        assert_eq!(detect(ivb, 0b111, 0b011), (false, false));
+        // The OS does not use XSAVE at all:
+        assert_eq!(detect(ivb & !(1 << 27), 0b111, 0b111), (false, false));
+        // The AVX state component is not enumerated:
+        assert_eq!(detect(ivb, 0b011, 0b111), (false, false));
+    }
+
+    /// The leaves of a processor with AVX and AVX-512F that reports `ebx` and
+    /// `ecx` in CPUID leaf 7, and whose OS enables `xcr0`.
+    fn avx512_leaves(ebx: u32, ecx: u32, xcr0: u64) -> CpuidLeaves {
+        CpuidLeaves {
+            max_basic_leaf: 0xd,
+            // xsave, osxsave, and avx:
+            proc_info_ecx: (1 << 26) | (1 << 27) | (1 << 28),
+            // avx512f:
+            extended_features_ebx: (1 << 16) | ebx,
+            extended_features_ecx: ecx,
+            proc_extended_state_components: 0xe7,
+            xcr0,
+            ..CpuidLeaves::default()
+        }
+    }
+
+    #[test]
+    fn avx512vbmi_vbmi2() {
+        // With AVX-512BW, `ecx` in sub-leaf `sub_leaf` of CPUID leaf 7:
+        let detect = |sub_leaf: u32, ecx: u32, xcr0: u64| {
+            let leaves = if sub_leaf == 0 {
+                avx512_leaves(1 << 30, ecx, xcr0)
+            } else {
+                CpuidLeaves {
+                    extended_features1_eax: ecx,
+                    ..avx512_leaves(1 << 30, 0, xcr0)
+                }
+            };
+            let value = decode_x86_features(&leaves);
+            (
+                value.test(Feature::avx512vbmi as u32),
+                value.test(Feature::avx512vbmi2 as u32),
+            )
+        };
+        assert_eq!(detect(0, 1 << 1, 0xe7), (true, false));
+        assert_eq!(detect(0, 1 << 6, 0xe7), (false, true));
+        assert_eq!(detect(0, (1 << 1) | (1 << 6), 0xe7), (true, true));
+        // The bits of sub-leaf 1 are unrelated:
+        assert_eq!(detect(1, (1 << 1) | (1 << 6), 0xe7), (false, false));
+        // The OS does not enable the AVX-512 state:
+        assert_eq!(detect(0, (1 << 1) | (1 << 6), 0x7), (false, false));
+    }
+
+    #[test]
+    fn avx512ifma() {
+        // `ebx` in leaf 7 and `eax1` in sub-leaf 1 of leaf 7:
+        let detect = |ebx: u32, eax1: u32, xcr0: u64| {
+            let leaves = CpuidLeaves {
+                extended_features1_eax: eax1,
+                ..avx512_leaves(ebx, 0, xcr0)
+            };
+            decode_x86_features(&leaves).test(Feature::avx512ifma as u32)
+        };
+        assert!(detect(1 << 21, 0, 0xe7));
+        // The VEX-encoded AVX-IFMA, CPUID.(EAX=7,ECX=1):EAX[23], is a
+        // different feature:
+        assert!(!detect(0, 1 << 23, 0xe7));
+        // The OS does not enable the AVX-512 state:
+        assert!(!detect(1 << 21, 0, 0x7));
+    }
+
+    #[test]
+    fn avx512_foundation() {
+        let detect = |ebx: u32, xcr0: u64| decode_x86_features(&avx512_leaves(ebx, 0, xcr0));
+        for &(bit, f) in &[
+            (28, Feature::avx512cd),
+            (17, Feature::avx512dq),
+            (30, Feature::avx512bw),
+            (31, Feature::avx512vl),
+        ] {
+            assert!(detect(1 << bit, 0xe7).test(f as u32), "{}", f.to_str());
+            // The OS does not enable the opmask, ZMM_Hi256, or Hi16_ZMM
+            // state components:
+            for &xcr0 in &[0x7, 0x67, 0xa7, 0xc7] {
+                let value = detect(1 << bit, xcr0);
+                assert!(!value.test(f as u32), "{} {:#x}", f.to_str(), xcr0);
+                assert!(!value.test(Feature::avx512f as u32));
+            }
+        }
+    }
+
+    /// Runs `detect_features_with` against a processor with AVX2 that reports
+    /// `avx10_ebx` in CPUID leaf 0x24, `avx512_ebx` in leaf 7, and whose OS
+    /// enables `xcr0`.
+    fn detect_avx10(avx10_ebx: u32, avx512_ebx: u32, xcr0: u64) -> cache::Initializer {
+        detect_avx10_on(false, avx10_ebx, avx512_ebx, xcr0)
+    }
+
+    /// Like `detect_avx10`, on a `hybrid` processor or not.
+    fn detect_avx10_on(
+        hybrid: bool,
+        avx10_ebx: u32,
+        avx512_ebx: u32,
+        xcr0: u64,
+    ) -> cache::Initializer {
+        // xsave, osxsave, avx, fma, and f16c:
+        let leaf1_ecx = (1 << 26) | (1 << 27) | (1 << 28) | (1 << 12) | (1 << 29);
+        let leaves = [
+            (0, 0, [0x24, 0, 0, 0]),
+            (1, 0, [0, 0, leaf1_ecx, 0]),
+            // avx2:
+            (7, 0, [1, (1 << 5) | avx512_ebx, 0, (hybrid as u32) << 15]),
+            (7, 1, [0, 0, 0, 1 << 19]),
+            (0xd, 0, [0b1110_0111, 0, 0, 0]),
+            (0x24, 0, [0, avx10_ebx, 0, 0]),
+        ];
+        detect_features_with(fake_cpuid(&leaves), |_| xcr0)
+    }
+
+    #[test]
+    fn avx10_256_only() {
+        // AVX10.1 with 128-bit and 256-bit vectors, but no legacy AVX-512
+        // enumeration:
+        let value = detect_avx10(1 | (1 << 16) | (1 << 17), 0, !0);
+        assert!(value.test(Feature::avx10_1_256 as u32));
+        assert!(value.test(Feature::avx2 as u32));
+        for &f in &[
+            Feature::avx10_1_512,
+            Feature::avx512f,
+            Feature::avx512vl,
+            Feature::avx512bw,
+            Feature::avx512dq,
+        ] {
+            assert!(!value.test(f as u32), "{} detected", f.to_str());
+        }
+    }
+
+    #[test]
+    fn avx10_512() {
+        // Granite Rapids: AVX10.1/512 and the legacy AVX-512 bits.
+        let avx512_ebx = (1 << 16) | (1 << 17) | (1 << 28) | (1 << 30) | (1 << 31);
+        let value = detect_avx10(1 | (0b111 << 16), avx512_ebx, !0);
+        assert!(value.test(Feature::avx10_1_256 as u32));
+        assert!(value.test(Feature::avx10_1_512 as u32));
+        assert!(value.test(Feature::avx512f as u32));
+        assert!(value.test(Feature::avx512vl as u32));
+    }
+
+    #[test]
+    fn avx10_not_usable() {
+        // The OS does not enable the AVX-512 state:
+        let value = detect_avx10(1 | (0b111 << 16), 0, 0b111);
+        assert!(value.test(Feature::avx2 as u32));
+        assert!(!value.test(Feature::avx10_1_256 as u32));
+        assert!(!value.test(Feature::avx10_1_512 as u32));
+        // Version 0 is not a valid AVX10 version:
+        let value = detect_avx10(0b111 << 16, 0, !0);
+        assert!(!value.test(Feature::avx10_1_256 as u32));
+    }
+
+    /// CPUID leaf 0x24 EBX on the P-cores of a hybrid processor, AVX10.1 with
+    /// 128-bit, 256-bit, and 512-bit vectors, and on its E-cores, with 128-bit
+    /// and 256-bit vectors only.
+    const P_CORE_AVX10_EBX: u32 = 0x0007_0001;
+    const E_CORE_AVX10_EBX: u32 = 0x0003_0001;
+
+    #[test]
+    fn avx10_hybrid() {
+        // Without the hybrid bit the executing core describes every core:
+        let value = detect_avx10_on(false, P_CORE_AVX10_EBX, 0, !0);
+        assert!(value.test(Feature::avx10_1_512 as u32));
+
+        // On a hybrid processor, the features are the same whichever core
+        // the detection ran on:
+        let p_core = detect_avx10_on(true, P_CORE_AVX10_EBX, 0, !0);
+        let e_core = detect_avx10_on(true, E_CORE_AVX10_EBX, 0, !0);
+        assert_eq!(p_core, e_core);
+        assert!(p_core.test(Feature::avx10_1_256 as u32));
+        assert!(!p_core.test(Feature::avx10_1_512 as u32));
+
+        // The same holds for the legacy AVX-512 bits of the P-cores:
+        let avx512_ebx = (1 << 16) | (1 << 17) | (1 << 28) | (1 << 30) | (1 << 31);
+        let p_core = detect_avx10_on(true, P_CORE_AVX10_EBX, avx512_ebx, !0);
+        assert_eq!(p_core, e_core);
+        for &f in &[
+            Feature::avx512f,
+            Feature::avx512dq,
+            Feature::avx512cd,
+            Feature::avx512bw,
+            Feature::avx512vl,
+        ] {
+            assert!(!p_core.test(f as u32), "{} detected", f.to_str());
+        }
+    }
+
+    #[test]
+    fn avx10_max_vlen_bits() {
+        let bits = |leaf7_1_edx, avx10_ebx| {
+            let leaves = [
+                (0, 0, [0x24, 0, 0, 0]),
+                (7, 0, [1, 0, 0, 1 << 15]),
+                (7, 1, [0, 0, 0, leaf7_1_edx]),
+                (0x24, 0, [0, avx10_ebx, 0, 0]),
+            ];
+            avx10_max_vlen_bits_with(fake_cpuid(&leaves))
+        };
+        // The executing core is reported:
+        assert_eq!(bits(1 << 19, P_CORE_AVX10_EBX), Some(512));
+        assert_eq!(bits(1 << 19, E_CORE_AVX10_EBX), Some(256));
+        assert_eq!(bits(1 << 19, 0x0001_0001), Some(128));
+        // AVX10 is not supported:
+        assert_eq!(bits(0, P_CORE_AVX10_EBX), None);
+        assert_eq!(bits(1 << 19, 0x0007_0000), None);
+    }
+
+    #[test]
+    fn monitor_line_sizes() {
+        let sizes = |max_basic_leaf, leaf1_ecx| {
+            let leaves = [
+                (0, 0, [max_basic_leaf, 0, 0, 0]),
+                (1, 0, [0, 0, leaf1_ecx, 0]),
+                // Skylake: 64-byte monitor lines.
+                (5, 0, [0x40, 0x40, 0x3, 0x0011_2120]),
+            ];
+            monitor_line_sizes_with(fake_cpuid(&leaves))
+        };
+        assert_eq!(sizes(0x16, 0x7ffa_fbbf), Some((64, 64)));
+        // MONITOR/MWAIT is not supported or disabled, e.g., in a VM:
+        assert_eq!(sizes(0x16, 0x7ffa_fbb7), None);
+        // Leaf 5 is not supported:
+        assert_eq!(sizes(0x4, 0x7ffa_fbbf), None);
+    }
+
+    #[test]
+    fn thread_director() {
+        let classes = |max_basic_leaf, eax, ecx| {
+            let leaves = [(0, 0, [max_basic_leaf, 0, 0, 0]), (6, 0, [eax, 0, ecx, 0])];
+            thread_director_classes_with(fake_cpuid(&leaves))
+        };
+        // Alder Lake: HFI and Thread Director with 4 classes.
+        assert_eq!(classes(0x20, 0x00df_8ff7, 0x0000_0409), Some(4));
+        // Sapphire Rapids and Tiger Lake: neither HFI nor Thread Director.
+        assert_eq!(classes(0x20, 0x0007_7ff7, 0x0000_0009), None);
+        // HFI only:
+        assert_eq!(classes(0x20, 1 << 19, 0), None);
+        // Leaf 6 is not supported:
+        assert_eq!(classes(0x5, 0x00df_8ff7, 0x0000_0409), None);
+    }
+
+    #[test]
+    fn avx10_version() {
+        let version = |max_basic_leaf, leaf7_1_edx, avx10_ebx| {
+            let leaves = [
+                (0, 0, [max_basic_leaf, 0, 0, 0]),
+                (7, 0, [1, 0, 0, 0]),
+                (7, 1, [0, 0, 0, leaf7_1_edx]),
+                (0x24, 0, [0, avx10_ebx, 0, 0]),
+            ];
+            avx10_version_with(fake_cpuid(&leaves))
+        };
+        assert_eq!(version(0x24, 1 << 19, 0x0007_0001), Some(1));
+        assert_eq!(version(0x29, 1 << 19, 0x0006_0002), Some(2));
+        // AVX10 is not supported:
+        assert_eq!(version(0x24, 0, 0x0007_0001), None);
+        assert_eq!(version(0x23, 1 << 19, 0x0007_0001), None);
+        assert_eq!(version(0x24, 1 << 19, 0x0007_0000), None);
+    }
+
+    #[test]
+    fn netbsd_xsave_features_sysctl() {
+        let detect = |xsave_features| {
+            detect_with_xstate(0b1110_0111, netbsd_xcr0(0b1110_0111, xsave_features))
+        };
+        // The kernel saves the AVX and AVX-512 state:
+        let value = detect(Some(0b1110_0111));
+        assert!(value.test(Feature::avx as u32));
+        assert!(value.test(Feature::avx512f as u32));
+        // ... only the AVX state:
+        let value = detect(Some(0b0000_0111));
+        assert!(value.test(Feature::avx as u32));
+        assert!(!value.test(Feature::avx512f as u32));
+        // ... neither, although `XCR0` enables them:
+        let value = detect(Some(0b0000_0011));
+        assert!(!value.test(Feature::avx as u32));
+        assert!(!value.test(Feature::avx512f as u32));
+        // The sysctl is not available, e.g., before NetBSD 9.0:
+        let value = detect(None);
+        assert!(value.test(Feature::avx512f as u32));
+    }
+
+    #[test]
+    #[cfg(all(target_os = "netbsd", feature = "libc"))]
+    fn netbsd_xsave_features_matches_xgetbv() {
+        let osxsave = unsafe { bit::test(__cpuid(1).ecx as usize, 27) };
+        if let (true, Some(xsave_features)) = (osxsave, netbsd_xsave_features()) {
+            assert_eq!(xsave_features, unsafe { _xgetbv(0) });
+        }
+    }
+
+    #[test]
+    fn windows_enabled_xstate_features() {
+        let detect = |enabled_xstate_features| {
+            detect_with_xstate(
+                0b1110_0111,
+                windows_xcr0(0b1110_0111, enabled_xstate_features),
+            )
+        };
+        // The OS saves the AVX and AVX-512 state:
+        let value = detect(Some(0b1110_0111));
+        assert!(value.test(Feature::avx as u32));
+        assert!(value.test(Feature::avx512f as u32));
+        // ... only the AVX state:
+        let value = detect(Some(0b0000_0111));
+        assert!(value.test(Feature::avx as u32));
+        assert!(!value.test(Feature::avx512f as u32));
+        // ... neither, although `XCR0` enables them:
+        let value = detect(Some(0b0000_0011));
+        assert!(!value.test(Feature::xsave as u32));
+        assert!(!value.test(Feature::avx as u32));
+        assert!(!value.test(Feature::avx512f as u32));
+        // `GetEnabledXStateFeatures` is not available:
+        let value = detect(None);
+        assert!(!value.test(Feature::avx as u32));
+
+        // The OS cannot enable more than `XCR0`:
+        assert_eq!(windows_xcr0(0b0000_0111, Some(!0)), 0b0000_0111);
+    }
+
+    /// Returns a fake `cpuid` function that reports `leaves`, as
+    /// `(leaf, sub_leaf, [eax, ebx, ecx, edx])`, and zeros for any other leaf.
+    fn fake_cpuid(leaves: &[(u32, u32, [u32; 4])]) -> impl Fn(u32, u32) -> CpuidResult + '_ {
+        move |leaf, sub_leaf| {
+            let [eax, ebx, ecx, edx] = leaves
+                .iter()
+                .find(|l| l.0 == leaf && l.1 == sub_leaf)
+                .map_or([0; 4], |l| l.2);
+            CpuidResult { eax, ebx, ecx, edx }
+        }
+    }
+
+    /// Returns the hypervisor vendor ID leaf for `vendor_id`.
+    fn hypervisor_leaf(vendor_id: &[u8; 12]) -> (u32, u32, [u32; 4]) {
+        let r = |i: usize| {
+            u32::from_le_bytes([
+                vendor_id[i],
+                vendor_id[i + 1],
+                vendor_id[i + 2],
+                vendor_id[i + 3],
+            ])
+        };
+        (0x4000_0000, 0, [0x4000_0001, r(0), r(4), r(8)])
+    }
+
+    /// sse, sse2, sse3, and ssse3, optionally with the hypervisor bit.
+    fn basic_leaf(hypervisor: bool) -> (u32, u32, [u32; 4]) {
+        let ecx = 1 | (1 << 9) | ((hypervisor as u32) << 31);
+        (1, 0, [0, 0, ecx, (1 << 25) | (1 << 26)])
+    }
+
+    #[test]
+    fn emulated_tcg() {
+        let leaves = [
+            (0, 0, [1, 0, 0, 0]),
+            basic_leaf(true),
+            hypervisor_leaf(b"TCGTCGTCGTCG"),
+        ];
+        assert!(likely_emulated_with(fake_cpuid(&leaves), |_| 0));
+    }
+
+    #[test]
+    fn emulated_implausible_features() {
+        // ssse3 without sse3:
+        let leaves = [
+            (0, 0, [1, 0, 0, 0]),
+            (1, 0, [0, 0, (1 << 9) | (1 << 31), (1 << 25) | (1 << 26)]),
+            hypervisor_leaf(b"KVMKVMKVM\0\0\0"),
+        ];
+        assert!(likely_emulated_with(fake_cpuid(&leaves), |_| 0));
+    }
+
+    #[test]
+    fn not_emulated_hypervisor() {
+        for vendor_id in &[b"KVMKVMKVM\0\0\0", b"Microsoft Hv", b"VMwareVMware"] {
+            let leaves = [
+                (0, 0, [1, 0, 0, 0]),
+                basic_leaf(true),
+                hypervisor_leaf(vendor_id),
+            ];
+            assert!(!likely_emulated_with(fake_cpuid(&leaves), |_| 0));
+        }
+    }
+
+    #[test]
+    fn not_emulated_without_hypervisor_bit() {
+        // The hypervisor vendor ID is meaningless without the hypervisor bit:
+        let leaves = [
+            (0, 0, [1, 0, 0, 0]),
+            basic_leaf(false),
+            hypervisor_leaf(b"TCGTCGTCGTCG"),
+        ];
+        assert!(!likely_emulated_with(fake_cpuid(&leaves), |_| 0));
+    }
+
+    #[test]
+    fn num_pmc() {
+        // Skylake: version 4, 4 counters.
+        let leaves = [(0, 0, [0x16, 0, 0, 0]), (0xa, 0, [0x0730_0404, 0, 0, 0])];
+        assert_eq!(num_pmc_with(fake_cpuid(&leaves)), Some(4));
+        let leaves = [(0, 0, [0xa, 0, 0, 0]), (0xa, 0, [0x0000_0802, 0, 0, 0])];
+        assert_eq!(num_pmc_with(fake_cpuid(&leaves)), Some(8));
+        // Version 0: not supported.
+        let leaves = [(0, 0, [0xa, 0, 0, 0]), (0xa, 0, [0x0000_0400, 0, 0, 0])];
+        assert_eq!(num_pmc_with(fake_cpuid(&leaves)), None);
+        // Leaf 0xA not supported.
+        let leaves = [(0, 0, [0x9, 0, 0, 0]), (0xa, 0, [0x0730_0404, 0, 0, 0])];
+        assert_eq!(num_pmc_with(fake_cpuid(&leaves)), None);
+    }
+
+    #[test]
+    fn tpause_likely_usable() {
+        let leaves = |max_basic_leaf: u32, hypervisor: bool, waitpkg: bool| {
+            [
+                (0, 0, [max_basic_leaf, 0, 0, 0]),
+                (1, 0, [0, 0, (hypervisor as u32) << 31, 0]),
+                (7, 0, [0, 0, (waitpkg as u32) << 5, 0]),
+                hypervisor_leaf(b"KVMKVMKVM\0\0\0"),
+            ]
+        };
+        let usable =
+            |leaves: &[(u32, u32, [u32; 4])]| tpause_likely_usable_with(fake_cpuid(leaves));
+        // Tremont or Alder Lake on bare metal:
+        assert!(usable(&leaves(0x20, false, true)));
+        assert!(!usable(&leaves(0x20, false, false)));
+        // A guest that is told about `WAITPKG`:
+        assert!(!usable(&leaves(0x20, true, true)));
+        assert!(!usable(&leaves(0x20, true, false)));
+        // Leaf 7 is not supported:
+        assert!(!usable(&leaves(0x6, false, true)));
+    }
+
+    #[test]
+    fn tsc_deadline_adjust() {
+        let has = |max_basic_leaf, leaf1_ecx, leaf7_ebx| {
+            let leaves = [
+                (0, 0, [max_basic_leaf, 0, 0, 0]),
+                (1, 0, [0, 0, leaf1_ecx, 0]),
+                (7, 0, [0, leaf7_ebx, 0, 0]),
+            ];
+            (
+                has_tsc_deadline_with(fake_cpuid(&leaves)),
+                has_tsc_adjust_with(fake_cpuid(&leaves)),
+            )
+        };
+        // Skylake has both:
+        assert_eq!(has(0x16, 0x7ffa_fbbf, 0x029c_6fbf), (true, true));
+        assert_eq!(has(0x16, 1 << 24, 0), (true, false));
+        assert_eq!(has(0x16, 0, 1 << 1), (false, true));
+        // Leaf 7 is not supported:
+        assert_eq!(has(0x6, 1 << 24, 1 << 1), (true, false));
+        assert_eq!(has(0, 1 << 24, 1 << 1), (false, false));
+    }
+
+    #[test]
+    fn la57() {
+        let la57_active = |leaf7_ecx, max_extended_leaf, eax, os_enabled| {
+            let leaves = [
+                (0, 0, [7, 0, 0, 0]),
+                (7, 0, [0, 0, leaf7_ecx, 0]),
+                (0x8000_0000, 0, [max_extended_leaf, 0, 0, 0]),
+                (0x8000_0008, 0, [eax, 0, 0, 0]),
+            ];
+            la57_active_with(fake_cpuid(&leaves), || os_enabled)
+        };
+        let la57 = 1 << 16;
+        // 48-bit linear and 39-bit physical addresses, e.g. Skylake:
+        assert!(!la57_active(0, 0x8000_0008, 0x3027, Some(true)));
+        // 57-bit linear and 46-bit physical addresses, e.g. Ice Lake-SP:
+        assert!(la57_active(la57, 0x8000_0008, 0x392e, Some(true)));
+        // ... but the kernel has not enabled 5-level paging:
+        assert!(!la57_active(la57, 0x8000_0008, 0x392e, Some(false)));
+        // ... or the OS does not tell:
+        assert!(!la57_active(la57, 0x8000_0008, 0x392e, None));
+        // Without the LA57 bit:
+        assert!(!la57_active(0, 0x8000_0008, 0x392e, Some(true)));
+        // The address-size leaf is not supported:
+        assert!(!la57_active(la57, 0x8000_0007, 0x392e, Some(true)));
+    }
+
+    #[test]
+    fn amd_topology() {
+        let topology = |vendor_id: &[u8; 12], max_extended_leaf, ext_ecx, leaf| {
+            let [leaf0, leaf1] = signature_leaves(vendor_id, 0x0087_0f10);
+            let leaves = [
+                leaf0,
+                leaf1,
+                (0x8000_0000, 0, [max_extended_leaf, 0, 0, 0]),
+                (0x8000_0001, 0, [0, 0, ext_ecx, 0]),
+                (0x8000_001e, 0, leaf),
+            ];
+            amd_topology_with(fake_cpuid(&leaves))
+        };
+        // Ryzen 7 3700X, the second thread of core 5:
+        let zen2 = [0xb, 0x0105, 0, 0];
+        assert_eq!(
+            topology(b"AuthenticAMD", 0x8000_001f, 0x75c2_37ff, zen2),
+            Some(AmdTopology {
+                extended_apic_id: 0xb,
+                core_id: 5,
+                threads_per_core: 2,
+                node_id: 0,
+                nodes_per_processor: 1,
+            })
+        );
+        // EPYC 7601, node 3 of 4:
+        let naples = [0x3d, 0x011e, 0x0303, 0];
+        assert_eq!(
+            topology(b"AuthenticAMD", 0x8000_001f, 0x75c2_37ff, naples).map(|t| (
+                t.core_id,
+                t.node_id,
+                t.nodes_per_processor
+            )),
+            Some((0x1e, 3, 4))
+        );
+        // No topology extensions:
+        assert_eq!(
+            topology(b"AuthenticAMD", 0x8000_001f, !(1 << 22), zen2),
+            None
+        );
+        // The leaf is not supported:
+        assert_eq!(
+            topology(b"AuthenticAMD", 0x8000_001d, 0x75c2_37ff, zen2),
+            None
+        );
+        // Not an AMD processor:
+        assert_eq!(
+            topology(b"GenuineIntel", 0x8000_001f, 0x75c2_37ff, zen2),
+            None
+        );
+    }
+
+    #[test]
+    fn sev_status() {
+        let amd = |leaf: [u32; 4], max_extended_leaf| {
+            let leaves = [
+                (
+                    0,
+                    0,
+                    [
+                        0x10,
+                        u32::from_le_bytes(*b"Auth"),
+                        u32::from_le_bytes(*b"cAMD"),
+                        u32::from_le_bytes(*b"enti"),
+                    ],
+                ),
+                (0x8000_0000, 0, [max_extended_leaf, 0, 0, 0]),
+                (0x8000_001f, 0, leaf),
+            ];
+            sev_status_with(fake_cpuid(&leaves))
+        };
+        // EPYC 7003 ("Milan"): SME, SEV, SEV-ES, and SEV-SNP.
+        assert_eq!(amd([0x0001_101f, 0, 0, 0], 0x8000_0023), SevStatus::SevSnp);
+        assert_eq!(amd([0b0_1011, 0, 0, 0], 0x8000_001f), SevStatus::SevEs);
+        assert_eq!(amd([0b0_0011, 0, 0, 0], 0x8000_001f), SevStatus::Sev);
+        // SME only:
+        assert_eq!(amd([0b0_0001, 0, 0, 0], 0x8000_001f), SevStatus::None);
+        // SEV-SNP requires SEV:
+        assert_eq!(amd([0b1_1000, 0, 0, 0], 0x8000_001f), SevStatus::None);
+        // The leaf is not supported:
+        assert_eq!(amd([0b1_1011, 0, 0, 0], 0x8000_001e), SevStatus::None);
+
+        // Not an AMD processor:
+        let leaves = [
+            (
+                0,
+                0,
+                [
+                    0x16,
+                    u32::from_le_bytes(*b"Genu"),
+                    u32::from_le_bytes(*b"ntel"),
+                    u32::from_le_bytes(*b"ineI"),
+                ],
+            ),
+            (0x8000_0000, 0, [0x8000_001f, 0, 0, 0]),
+            (0x8000_001f, 0, [0b1_1011, 0, 0, 0]),
+        ];
+        assert_eq!(sev_status_with(fake_cpuid(&leaves)), SevStatus::None);
+    }
+
+    #[test]
+    fn tdx_guest() {
+        let signature = [
+            0,
+            u32::from_le_bytes(*b"Inte"),
+            u32::from_le_bytes(*b"    "),
+            u32::from_le_bytes(*b"lTDX"),
+        ];
+        let leaves = [(0, 0, [0x21, 0, 0, 0]), (0x21, 0, signature)];
+        assert!(is_tdx_guest_with(fake_cpuid(&leaves)));
+        // Leaf 0x21 is not supported:
+        let leaves = [(0, 0, [0x20, 0, 0, 0]), (0x21, 0, signature)];
+        assert!(!is_tdx_guest_with(fake_cpuid(&leaves)));
+        // Another signature:
+        let leaves = [(0, 0, [0x21, 0, 0, 0]), (0x21, 0, [0; 4])];
+        assert!(!is_tdx_guest_with(fake_cpuid(&leaves)));
+    }
+
+    /// Returns the basic leaves of a processor of `vendor_id` that reports
+    /// `version` in CPUID.1:EAX.
+    fn signature_leaves(vendor_id: &[u8; 12], version: u32) -> [(u32, u32, [u32; 4]); 2] {
+        let r = |i: usize| {
+            u32::from_le_bytes([
+                vendor_id[i],
+                vendor_id[i + 1],
+                vendor_id[i + 2],
+                vendor_id[i + 3],
+            ])
+        };
+        [(0, 0, [0xd, r(0), r(8), r(4)]), (1, 0, [version, 0, 0, 0])]
+    }
+
+    #[test]
+    fn cpu_signature() {
+        let signature = |vendor_id: &[u8; 12], version| {
+            cpu_signature_with(fake_cpuid(&signature_leaves(vendor_id, version))).unwrap()
+        };
+        let sig = |vendor_id: &[u8; 12], family, model, stepping| CpuSignature {
+            vendor_id: *vendor_id,
+            family,
+            model,
+            stepping,
+        };
+        let intel = b"GenuineIntel";
+        let amd = b"AuthenticAMD";
+        // Core i7-2600 (Sandy Bridge):
+        assert_eq!(signature(intel, 0x0002_06a7), sig(intel, 0x6, 0x2a, 7));
+        // Core i7-6700K (Skylake):
+        assert_eq!(signature(intel, 0x0005_06e3), sig(intel, 0x6, 0x5e, 3));
+        // Pentium 4 (Northwood):
+        assert_eq!(signature(intel, 0x0000_0f29), sig(intel, 0xf, 0x2, 9));
+        // Pentium (P5), which has no extended model:
+        assert_eq!(signature(intel, 0x0001_0543), sig(intel, 0x5, 0x4, 3));
+        // Ryzen 9 5950X (Zen 3):
+        assert_eq!(signature(amd, 0x00a2_0f10), sig(amd, 0x19, 0x21, 0));
+
+        // Leaf 1 is not supported:
+        let leaves = [(0, 0, [0, 0, 0, 0]), (1, 0, [0x0002_06a7, 0, 0, 0])];
+        assert_eq!(cpu_signature_with(fake_cpuid(&leaves)), None);
+
+        #[cfg(all(target_arch = "x86_64", not(miri)))]
+        assert!(__cpu_signature().is_some());
+    }
+
+    #[test]
+    fn hwrng_trustworthy() {
+        let trustworthy = |vendor_id: &[u8; 12], version: u32, rdrand: bool, rdseed: bool| {
+            let [leaf0, mut leaf1] = signature_leaves(vendor_id, version);
+            leaf1.2[2] = (rdrand as u32) << 30;
+            let leaf7 = (7, 0, [0, (rdseed as u32) << 18, 0, 0]);
+            hwrng_trustworthy_with(fake_cpuid(&[leaf0, leaf1, leaf7]))
+        };
+        let intel = b"GenuineIntel";
+        let amd = b"AuthenticAMD";
+        // Skylake and Zen 3:
+        assert!(trustworthy(intel, 0x0005_06e3, true, true));
+        assert!(trustworthy(amd, 0x00a2_0f10, true, true));
+        // Ivy Bridge, with `rdrand` but not `rdseed`:
+        assert!(trustworthy(intel, 0x0003_06a9, true, false));
+        // Neither instruction is supported:
+        assert!(!trustworthy(intel, 0x0002_06a7, false, false));
+        // Piledriver, Jaguar, Zen 2 (Matisse), and Zen 5 have RNG errata:
+        for &version in &[0x0060_0f20, 0x0070_0f01, 0x0087_0f10, 0x00b4_0f40] {
+            assert!(!trustworthy(amd, version, true, true), "{:#x}", version);
+        }
+        // Zen 2 (Rome) does not:
+        assert!(trustworthy(amd, 0x0083_0f10, true, true));
+    }
+
+    #[test]
+    fn avx_sse_transition_penalty() {
+        let penalty = |vendor_id: &[u8; 12], version| {
+            let leaves = signature_leaves(vendor_id, version);
+            has_avx_sse_transition_penalty(cpu_signature_with(fake_cpuid(&leaves)).unwrap())
+        };
+        let intel = b"GenuineIntel";
+        // Sandy Bridge, Ivy Bridge-EP, Haswell, and Broadwell-EP:
+        for &version in &[0x0002_06a7, 0x0003_06e4, 0x0003_06c3, 0x0004_06f1] {
+            assert!(penalty(intel, version), "{:#x}", version);
+        }
+        // Nehalem (no AVX), Skylake, Skylake-SP, and Ice Lake:
+        for &version in &[0x0001_06a5, 0x0005_06e3, 0x0005_0654, 0x0007_06e5] {
+            assert!(!penalty(intel, version), "{:#x}", version);
+        }
+        // Zen 2 and a family 0xF Intel processor with a matching model:
+        assert!(!penalty(b"AuthenticAMD", 0x0083_0f10));
+        assert!(!penalty(intel, 0x0002_0fa0));
+    }
+
+    #[test]
+    fn host_cpuid() {
+        let calls = RefCell::new(0);
+        let value = detect_features_with(
+            |leaf, sub_leaf| {
+                *calls.borrow_mut() += 1;
+                unsafe { __cpuid_count(leaf, sub_leaf) }
+            },
+            |xcr| unsafe { _xgetbv(xcr) },
+        );
+        assert!(calls.into_inner() <= cpuid_calls().len());
+        assert_eq!(value, detect_features());
+    }
+}
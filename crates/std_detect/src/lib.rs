@@ -15,9 +15,10 @@
 #![feature(const_fn, staged_api, stdsimd, doc_cfg, allow_internal_unstable)]
 #![allow(clippy::shadow_reuse)]
 #![deny(clippy::missing_inline_in_public_items)]
-#![cfg_attr(all(target_os = "freebsd", target_arch = "aarch64"), feature(asm))]
+#![cfg_attr(target_arch = "aarch64", feature(asm))]
 #![cfg_attr(test, allow(unused_imports))]
 #![cfg_attr(feature = "std_detect_file_io", feature(vec_spare_capacity))]
+#![cfg_attr(feature = "std_detect_thread_local_cache", feature(thread_local))]
 #![no_std]
 
 #[cfg_attr(feature = "rustc-dep-of-std", allow(unused_extern_crates))]
@@ -31,3 +32,20 @@ extern crate std;
 #[doc(hidden)]
 #[unstable(feature = "stdsimd", issue = "27731")]
 pub mod detect;
+
+/// Performs run-time feature detection for the feature called `name`.
+///
+/// This is the run-time counterpart of the `is_{arch}_feature_detected!`
+/// macros for callers that only know the feature name at run-time. The name
+/// is looked up in the feature table of the current target architecture.
+///
+/// Returns `None` if `name` is not a feature of the current target
+/// architecture, or if it cannot be detected at run-time. Otherwise returns
+/// `Some(true)` if the feature is enabled and `Some(false)` if it is not.
+#[inline]
+#[unstable(feature = "stdsimd", issue = "27731")]
+pub fn __is_feature_detected(name: &str) -> Option<bool> {
+    detect::Feature::from_str(name)
+        .ok()
+        .map(detect::Feature::is_detected)
+}
@@ -19,6 +19,135 @@ fn all() {
     }
 }
 
+#[test]
+fn features_order() {
+    let names = || std_detect::detect::features().map(|(f, _)| f);
+    assert!(names().eq(names()));
+    #[cfg(target_arch = "x86_64")]
+    assert_eq!(
+        names().take(8).collect::<Vec<_>>(),
+        [
+            "aes",
+            "pclmulqdq",
+            "rdrand",
+            "rdseed",
+            "tsc",
+            "mmx",
+            "sse",
+            "sse2"
+        ]
+    );
+}
+
+#[test]
+fn initialize() {
+    std_detect::detect::__initialize_detection();
+    assert!(std_detect::detect::__detection_initialized());
+}
+
+#[test]
+fn snapshot() {
+    use std_detect::detect::FeatureSnapshot;
+    let snapshot = FeatureSnapshot::current();
+    assert_eq!(snapshot, FeatureSnapshot::current());
+    let bytes = snapshot.to_bytes();
+    assert_eq!(FeatureSnapshot::from_bytes(bytes), snapshot);
+    assert_eq!(FeatureSnapshot::from_bytes(bytes).to_bytes(), bytes);
+
+    let bytes = [0x5a, 0, 0, 0, 0, 0, 0x01, 0, 0, 0x02, 0, 0, 0, 0, 0, 0];
+    assert_eq!(FeatureSnapshot::from_bytes(bytes).to_bytes(), bytes);
+    assert_eq!(FeatureSnapshot::SIZE, bytes.len());
+}
+
+#[test]
+fn report() {
+    let report = std_detect::detect::__detection_report().to_string();
+    println!("{}", report);
+    assert!(report.starts_with("target_arch: "));
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    assert!(report.contains("\nsse2: "));
+    #[cfg(target_arch = "aarch64")]
+    assert!(report.contains("\nneon: "));
+}
+
+#[test]
+fn detection_source() {
+    use std_detect::detect::{self, DetectionSource};
+    assert_eq!(detect::__detection_source("not-a-feature"), None);
+    #[cfg(target_arch = "x86_64")]
+    assert_eq!(
+        detect::__detection_source("sse2"),
+        Some(DetectionSource::Cpuid)
+    );
+    #[cfg(all(target_arch = "aarch64", target_os = "linux"))]
+    {
+        if detect::features().any(|(f, enabled)| f == "asimd" && enabled) {
+            assert!(matches!(
+                detect::__detection_source("asimd"),
+                Some(DetectionSource::Hwcap) | Some(DetectionSource::CpuInfo)
+            ));
+        }
+    }
+    for (f, enabled) in detect::features() {
+        let source = detect::__detection_source(f).unwrap();
+        println!("{}: {:?}", f, source);
+        // Features that are enabled at compile-time are detected even if they
+        // are not detected at run-time:
+        assert!(!enabled || source != DetectionSource::NotDetected);
+    }
+}
+
+#[test]
+#[cfg(target_os = "macos")]
+fn translated() {
+    let translated = std_detect::detect::__is_translated();
+    println!("translated: {}", translated);
+    // Rosetta 2 only translates x86_64 processes:
+    #[cfg(target_arch = "aarch64")]
+    assert!(!translated);
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_arch = "x86_64", feature = "libc"))]
+fn cet_shadow_stack() {
+    println!(
+        "shadow stack active: {}",
+        std_detect::detect::__cet_shadow_stack_active()
+    );
+}
+
+#[test]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn x86_by_name() {
+    use std_detect::__is_feature_detected;
+    assert_eq!(
+        __is_feature_detected("sse2"),
+        Some(is_x86_feature_detected!("sse2"))
+    );
+    assert_eq!(
+        __is_feature_detected("abm"),
+        Some(is_x86_feature_detected!("lzcnt"))
+    );
+    assert_eq!(__is_feature_detected("sse314"), None);
+    assert_eq!(__is_feature_detected("neon"), None);
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn aarch64_by_name() {
+    use std_detect::__is_feature_detected;
+    assert_eq!(
+        __is_feature_detected("neon"),
+        Some(is_aarch64_feature_detected!("neon"))
+    );
+    assert_eq!(
+        __is_feature_detected("asimd"),
+        Some(is_aarch64_feature_detected!("neon"))
+    );
+    assert_eq!(__is_feature_detected("ras"), None);
+    assert_eq!(__is_feature_detected("sse2"), None);
+}
+
 #[test]
 #[cfg(all(target_arch = "arm", any(target_os = "linux", target_os = "android")))]
 fn arm_linux() {
@@ -46,6 +175,67 @@ fn aarch64_linux() {
     println!("rcpc: {}", is_aarch64_feature_detected!("rcpc"));
     println!("dotprod: {}", is_aarch64_feature_detected!("dotprod"));
     println!("tme: {}", is_aarch64_feature_detected!("tme"));
+    println!("lse2: {}", is_aarch64_feature_detected!("lse2"));
+    println!("fhm: {}", is_aarch64_feature_detected!("fhm"));
+    println!("sha512: {}", is_aarch64_feature_detected!("sha512"));
+    println!("sha3: {}", is_aarch64_feature_detected!("sha3"));
+    println!("sm4: {}", is_aarch64_feature_detected!("sm4"));
+    println!("fcma: {}", is_aarch64_feature_detected!("fcma"));
+    println!("jsconv: {}", is_aarch64_feature_detected!("jsconv"));
+    println!("dpb: {}", is_aarch64_feature_detected!("dpb"));
+    println!("dpb2: {}", is_aarch64_feature_detected!("dpb2"));
+    println!("flagm: {}", is_aarch64_feature_detected!("flagm"));
+    println!("flagm2: {}", is_aarch64_feature_detected!("flagm2"));
+    println!("aes: {}", is_aarch64_feature_detected!("aes"));
+    println!("ecv: {}", is_aarch64_feature_detected!("ecv"));
+    println!("rprfm: {}", is_aarch64_feature_detected!("rprfm"));
+    println!("sve2: {}", is_aarch64_feature_detected!("sve2"));
+    println!("sve2p1: {}", is_aarch64_feature_detected!("sve2p1"));
+    println!("sme: {}", is_aarch64_feature_detected!("sme"));
+    println!("sme2: {}", is_aarch64_feature_detected!("sme2"));
+    println!("sme2p1: {}", is_aarch64_feature_detected!("sme2p1"));
+    println!("faminmax: {}", is_aarch64_feature_detected!("faminmax"));
+    println!("lut: {}", is_aarch64_feature_detected!("lut"));
+    println!("predres: {}", is_aarch64_feature_detected!("predres"));
+}
+
+#[test]
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+fn aarch64_macos() {
+    println!("fp: {}", is_aarch64_feature_detected!("fp"));
+    println!("fp16: {}", is_aarch64_feature_detected!("fp16"));
+    println!("asimd: {}", is_aarch64_feature_detected!("asimd"));
+    println!("crc: {}", is_aarch64_feature_detected!("crc"));
+    println!("crypto: {}", is_aarch64_feature_detected!("crypto"));
+    println!("lse: {}", is_aarch64_feature_detected!("lse"));
+    println!("lse2: {}", is_aarch64_feature_detected!("lse2"));
+    println!("rdm: {}", is_aarch64_feature_detected!("rdm"));
+    println!("rcpc: {}", is_aarch64_feature_detected!("rcpc"));
+    println!("dotprod: {}", is_aarch64_feature_detected!("dotprod"));
+    println!("fhm: {}", is_aarch64_feature_detected!("fhm"));
+    println!("sha512: {}", is_aarch64_feature_detected!("sha512"));
+    println!("sha3: {}", is_aarch64_feature_detected!("sha3"));
+    println!("fcma: {}", is_aarch64_feature_detected!("fcma"));
+    println!("jsconv: {}", is_aarch64_feature_detected!("jsconv"));
+    println!("dpb: {}", is_aarch64_feature_detected!("dpb"));
+    println!("dpb2: {}", is_aarch64_feature_detected!("dpb2"));
+    println!("flagm: {}", is_aarch64_feature_detected!("flagm"));
+    println!("flagm2: {}", is_aarch64_feature_detected!("flagm2"));
+    println!("aes: {}", is_aarch64_feature_detected!("aes"));
+    println!("ecv: {}", is_aarch64_feature_detected!("ecv"));
+    println!("rprfm: {}", is_aarch64_feature_detected!("rprfm"));
+    println!("sve2: {}", is_aarch64_feature_detected!("sve2"));
+    println!("sve2p1: {}", is_aarch64_feature_detected!("sve2p1"));
+    println!("sme: {}", is_aarch64_feature_detected!("sme"));
+    println!("sme2: {}", is_aarch64_feature_detected!("sme2"));
+    println!("sme2p1: {}", is_aarch64_feature_detected!("sme2p1"));
+    println!("faminmax: {}", is_aarch64_feature_detected!("faminmax"));
+    println!("lut: {}", is_aarch64_feature_detected!("lut"));
+    println!("predres: {}", is_aarch64_feature_detected!("predres"));
+    // Every Apple silicon processor supports these:
+    assert!(is_aarch64_feature_detected!("asimd"));
+    assert!(is_aarch64_feature_detected!("crc"));
+    assert!(is_aarch64_feature_detected!("lse"));
 }
 
 #[test]
@@ -54,6 +244,12 @@ fn powerpc_linux() {
     println!("altivec: {}", is_powerpc_feature_detected!("altivec"));
     println!("vsx: {}", is_powerpc_feature_detected!("vsx"));
     println!("power8: {}", is_powerpc_feature_detected!("power8"));
+    println!("power9: {}", is_powerpc_feature_detected!("power9"));
+    println!("power10: {}", is_powerpc_feature_detected!("power10"));
+    println!("htm: {}", is_powerpc_feature_detected!("htm"));
+    println!("htm-nosc: {}", is_powerpc_feature_detected!("htm-nosc"));
+    println!("ebb: {}", is_powerpc_feature_detected!("ebb"));
+    println!("isel: {}", is_powerpc_feature_detected!("isel"));
 }
 
 #[test]
@@ -62,6 +258,12 @@ fn powerpc64_linux() {
     println!("altivec: {}", is_powerpc64_feature_detected!("altivec"));
     println!("vsx: {}", is_powerpc64_feature_detected!("vsx"));
     println!("power8: {}", is_powerpc64_feature_detected!("power8"));
+    println!("power9: {}", is_powerpc64_feature_detected!("power9"));
+    println!("power10: {}", is_powerpc64_feature_detected!("power10"));
+    println!("htm: {}", is_powerpc64_feature_detected!("htm"));
+    println!("htm-nosc: {}", is_powerpc64_feature_detected!("htm-nosc"));
+    println!("ebb: {}", is_powerpc64_feature_detected!("ebb"));
+    println!("isel: {}", is_powerpc64_feature_detected!("isel"));
 }
 
 #[test]
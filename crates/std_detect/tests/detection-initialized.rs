@@ -0,0 +1,20 @@
+#![feature(stdsimd)]
+#![cfg(any(
+    target_arch = "arm",
+    target_arch = "aarch64",
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64"
+))]
+
+extern crate std_detect;
+
+use std_detect::detect;
+
+#[test]
+fn initialized_after_first_use() {
+    assert!(!detect::__detection_initialized());
+    let _ = detect::features().count();
+    assert!(detect::__detection_initialized());
+}
@@ -0,0 +1,34 @@
+#![feature(stdsimd)]
+#![allow(clippy::unwrap_used)]
+#![cfg(any(
+    target_arch = "arm",
+    target_arch = "aarch64",
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64"
+))]
+
+extern crate std_detect;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std_detect::detect;
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn observer(_: &detect::Initializer) {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test]
+fn observer_is_invoked_once() {
+    detect::__set_detection_observer(observer);
+    let threads: Vec<_> = (0..8)
+        .map(|_| std::thread::spawn(|| detect::features().count()))
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    let _ = detect::features().count();
+    assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,192 @@
+#![feature(stdsimd)]
+#![cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+#![cfg(not(miri))]
+
+extern crate std_detect;
+
+use std_detect::detect::{self, FeatureSnapshot, RawDetectionInput};
+
+/// Asserts that the features in `enabled` are enabled in `snap`, and those in
+/// `disabled` are not.
+fn assert_features(snap: FeatureSnapshot, enabled: &[&str], disabled: &[&str]) {
+    let is_enabled = |name: &str| {
+        snap.features()
+            .find(|&(f, _)| f == name)
+            .unwrap_or_else(|| panic!("unknown feature {}", name))
+            .1
+    };
+    for &f in enabled {
+        assert!(is_enabled(f), "{} not enabled", f);
+    }
+    for &f in disabled {
+        assert!(!is_enabled(f), "{} enabled", f);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn vendor_leaf(max_basic_leaf: u32, vendor_id: &[u8; 12]) -> (u32, u32, [u32; 4]) {
+    let r = |i: usize| {
+        u32::from_le_bytes([
+            vendor_id[i],
+            vendor_id[i + 1],
+            vendor_id[i + 2],
+            vendor_id[i + 3],
+        ])
+    };
+    (0, 0, [max_basic_leaf, r(0), r(8), r(4)])
+}
+
+#[test]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn core_i7_6700k() {
+    // Skylake, with TSX and MPX.
+    let leaves = [
+        vendor_leaf(0x16, b"GenuineIntel"),
+        (1, 0, [0x0005_06e3, 0x0010_0800, 0x7ffa_fbbf, 0xbfeb_fbff]),
+        (7, 0, [0, 0x029c_6fbf, 0, 0x9c00_0000]),
+        (0xd, 0, [0x1f, 0, 0, 0]),
+        (0xd, 1, [0xf, 0, 0, 0]),
+        (0x8000_0000, 0, [0x8000_0008, 0, 0, 0]),
+        (0x8000_0001, 0, [0, 0, 0x121, 0x2c10_0800]),
+    ];
+    let snap = detect::__evaluate_features_from(RawDetectionInput::Cpuid {
+        leaves: &leaves,
+        xcr0: 0x1f,
+    });
+    assert_features(
+        snap,
+        &[
+            "sse4.2",
+            "popcnt",
+            "avx",
+            "avx2",
+            "fma",
+            "bmi1",
+            "bmi2",
+            "lzcnt",
+            "adx",
+            "rdseed",
+            "rtm",
+            "xsavec",
+            "xsaves",
+            "cmpxchg16b",
+        ],
+        &["avx512f", "sha", "sse4a", "gfni", "vaes"],
+    );
+
+    // The OS does not enable the AVX state:
+    let snap = detect::__evaluate_features_from(RawDetectionInput::Cpuid {
+        leaves: &leaves,
+        xcr0: 0x3,
+    });
+    assert_features(
+        snap,
+        &["sse4.2", "bmi2"],
+        &["avx", "avx2", "fma", "f16c", "xsave"],
+    );
+}
+
+#[test]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn xeon_gold_6248() {
+    // Cascade Lake, with AVX-512 VNNI.
+    let leaves = [
+        vendor_leaf(0x16, b"GenuineIntel"),
+        (1, 0, [0x0005_0657, 0x0040_0800, 0x7ffe_fbff, 0xbfeb_fbff]),
+        (7, 0, [0, 0xd39f_fffb, 0x0000_0818, 0xbc00_0400]),
+        (0xd, 0, [0x2ff, 0, 0, 0]),
+        (0xd, 1, [0xf, 0, 0, 0]),
+        (0x8000_0000, 0, [0x8000_0008, 0, 0, 0]),
+        (0x8000_0001, 0, [0, 0, 0x121, 0x2c10_0800]),
+    ];
+    let snap = detect::__evaluate_features_from(RawDetectionInput::Cpuid {
+        leaves: &leaves,
+        xcr0: 0x2ff,
+    });
+    assert_features(
+        snap,
+        &[
+            "avx2",
+            "avx512f",
+            "avx512cd",
+            "avx512bw",
+            "avx512dq",
+            "avx512vl",
+            "avx512vnni",
+        ],
+        &[
+            "avx512ifma",
+            "avx512vbmi",
+            "avx512bf16",
+            "sha",
+            "avx10.1-256",
+        ],
+    );
+}
+
+#[test]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn ryzen_7_3700x() {
+    // Zen 2.
+    let leaves = [
+        vendor_leaf(0x10, b"AuthenticAMD"),
+        (1, 0, [0x0087_0f10, 0x0010_0800, 0x7ed8_320b, 0x178b_fbff]),
+        (7, 0, [0, 0x219c_91a9, 0x0040_0004, 0]),
+        (0xd, 0, [0x207, 0, 0, 0]),
+        (0xd, 1, [0xf, 0, 0, 0]),
+        (0x8000_0000, 0, [0x8000_001f, 0, 0, 0]),
+        (0x8000_0001, 0, [0, 0, 0x75c2_37ff, 0x2fd3_fbff]),
+    ];
+    let snap = detect::__evaluate_features_from(RawDetectionInput::Cpuid {
+        leaves: &leaves,
+        xcr0: 0x207,
+    });
+    assert_features(
+        snap,
+        &[
+            "avx2", "fma", "f16c", "bmi2", "sha", "adx", "sse4a", "lzcnt", "popcnt", "rdrand",
+            "rdseed", "xsavec",
+        ],
+        &["avx512f", "tbm", "rtm", "gfni"],
+    );
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn neoverse_n1() {
+    // ID_AA64ISAR0_EL1, ID_AA64ISAR1_EL1, ID_AA64MMFR0_EL1, ID_AA64MMFR2_EL1,
+    // ID_AA64PFR0_EL1:
+    let n1 = [
+        0x0000_1000_1021_1120,
+        0x0000_0000_0010_0001,
+        0x0000_0000_0010_1125,
+        0x0000_0000_0000_1011,
+        0x1100_0000_1011_1111,
+    ];
+    let snap = detect::__evaluate_features_from(RawDetectionInput::IdRegisters(&[n1]));
+    assert_features(
+        snap,
+        &[
+            "fp", "neon", "aes", "pmull", "crypto", "crc", "lse", "rdm", "fp16", "dotprod", "rcpc",
+            "dpb",
+        ],
+        &["sve", "sha3", "fhm", "fcma", "jsconv", "predres", "ecv"],
+    );
+
+    // ID_AA64MMFR0_EL1.ECV, as on the Apple M2:
+    let ecv = [n1[0], n1[1], n1[2] | 1 << 60, n1[3], n1[4]];
+    let snap = detect::__evaluate_features_from(RawDetectionInput::IdRegisters(&[ecv]));
+    assert_features(snap, &["ecv", "dotprod"], &[]);
+    // ... is only reported if every core implements it:
+    let snap = detect::__evaluate_features_from(RawDetectionInput::IdRegisters(&[ecv, n1]));
+    assert_features(snap, &["dotprod"], &["ecv"]);
+
+    // A core without the crypto extension:
+    let no_crypto = [n1[0] & !0xfff0, n1[1], n1[2], n1[3], n1[4]];
+    let snap = detect::__evaluate_features_from(RawDetectionInput::IdRegisters(&[n1, no_crypto]));
+    assert_features(
+        snap,
+        &["neon", "crc", "dotprod"],
+        &["aes", "pmull", "crypto"],
+    );
+}
@@ -0,0 +1,32 @@
+#![feature(stdsimd)]
+#![cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#![cfg(not(miri))]
+
+#[macro_use]
+extern crate std_detect;
+
+use std_detect::detect::{self, RawDetectionInput};
+
+#[test]
+fn movrs_round_trip() {
+    // A CPU that only reports MOVRS, in CPUID.(EAX=07H,ECX=01H):EAX[31]:
+    let leaves = [
+        (0, 0, [7, 0, 0, 0]),
+        (7, 0, [1, 0, 0, 0]),
+        (7, 1, [1 << 31, 0, 0, 0]),
+    ];
+    let snap = detect::__evaluate_features_from(RawDetectionInput::Cpuid {
+        leaves: &leaves,
+        xcr0: 0,
+    });
+    detect::__install_feature_snapshot(snap);
+    assert!(is_x86_feature_detected!("movrs"));
+    assert!(!is_x86_feature_detected!("clwb"));
+    assert_eq!(
+        detect::features()
+            .filter(|&(_, enabled)| enabled)
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>(),
+        ["movrs"]
+    );
+}
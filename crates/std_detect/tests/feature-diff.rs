@@ -0,0 +1,56 @@
+#![feature(stdsimd)]
+#![cfg(any(
+    target_arch = "arm",
+    target_arch = "aarch64",
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64"
+))]
+
+extern crate std_detect;
+
+use std_detect::detect::{self, FeatureDiff, FeatureSnapshot};
+
+#[test]
+fn diff_between_snapshots() {
+    let none = FeatureSnapshot::from_bytes([0; FeatureSnapshot::SIZE]);
+    let all = FeatureSnapshot::from_bytes([0xff; FeatureSnapshot::SIZE]);
+    let current = FeatureSnapshot::current();
+    let enabled: Vec<_> = detect::features()
+        .filter(|&(_, enabled)| enabled)
+        .map(|(name, _)| name)
+        .collect();
+    let disabled: Vec<_> = detect::features()
+        .filter(|&(_, enabled)| !enabled)
+        .map(|(name, _)| name)
+        .collect();
+
+    assert!(FeatureDiff::between(&all, &all).is_empty());
+    assert!(FeatureDiff::between(&current, &current).is_empty());
+
+    let diff = FeatureDiff::between(&none, &current);
+    assert_eq!(diff.gained().collect::<Vec<_>>(), enabled);
+    assert_eq!(diff.lost().count(), 0);
+
+    let diff = FeatureDiff::between(&all, &current);
+    assert_eq!(diff.gained().count(), 0);
+    assert_eq!(diff.lost().collect::<Vec<_>>(), disabled);
+
+    let diff = FeatureDiff::between(&none, &all);
+    assert_eq!(diff.gained().count(), detect::features().count());
+    assert_eq!(diff.lost().count(), 0);
+}
+
+#[test]
+fn diff_from_snapshot() {
+    // The CPU does not change while the test runs:
+    let current = FeatureSnapshot::current();
+    assert!(detect::__diff_from_snapshot(&current).is_empty());
+
+    let none = FeatureSnapshot::from_bytes([0; FeatureSnapshot::SIZE]);
+    assert_eq!(
+        detect::__diff_from_snapshot(&none),
+        FeatureDiff::between(&none, &current)
+    );
+}
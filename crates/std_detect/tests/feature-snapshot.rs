@@ -0,0 +1,49 @@
+#![feature(stdsimd)]
+#![cfg(any(
+    target_arch = "arm",
+    target_arch = "aarch64",
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64"
+))]
+
+#[macro_use]
+extern crate std_detect;
+
+use std_detect::detect::{self, FeatureSnapshot};
+
+#[test]
+fn install_feature_snapshot() {
+    // A snapshot with every feature enabled:
+    detect::__install_feature_snapshot(FeatureSnapshot::from_bytes([0xff; FeatureSnapshot::SIZE]));
+    assert!(detect::__detection_initialized());
+    assert!(detect::features().all(|(_, enabled)| enabled));
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        assert!(is_x86_feature_detected!("avx512vp2intersect"));
+        assert!(is_x86_feature_detected!("rtm"));
+    }
+    #[cfg(target_arch = "aarch64")]
+    assert!(is_aarch64_feature_detected!("tme"));
+    assert_eq!(
+        detect::__detection_source(detect::features().next().unwrap().0),
+        Some(detect::DetectionSource::Snapshot)
+    );
+
+    // Detecting the features again does not change the source of the cached
+    // ones:
+    let _ = detect::__diff_from_snapshot(&FeatureSnapshot::current());
+    assert_eq!(
+        detect::__detection_source(detect::features().next().unwrap().0),
+        Some(detect::DetectionSource::Snapshot)
+    );
+
+    // Once the cache has been initialized, installing a snapshot does nothing:
+    detect::__install_feature_snapshot(FeatureSnapshot::from_bytes([0; FeatureSnapshot::SIZE]));
+    assert!(detect::features().all(|(_, enabled)| enabled));
+    assert_ne!(
+        FeatureSnapshot::current().to_bytes(),
+        [0; FeatureSnapshot::SIZE]
+    );
+}
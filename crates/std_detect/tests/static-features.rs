@@ -0,0 +1,29 @@
+#![cfg(all(
+    feature = "std_detect_env_override",
+    any(target_arch = "x86", target_arch = "x86_64")
+))]
+#![feature(stdsimd)]
+
+#[macro_use]
+extern crate std_detect;
+
+#[test]
+fn statically_enabled_features_are_detected() {
+    // Disable the features at run-time before they are detected for the first
+    // time:
+    std::env::set_var("RUST_STD_DETECT_UNSTABLE", "avx avx2");
+    for (name, enabled) in std_detect::detect::features() {
+        if name == "avx" || name == "avx2" {
+            assert!(!enabled, "{} detected", name);
+        }
+    }
+    // The macros still report the features that are enabled at compile-time:
+    assert_eq!(
+        is_x86_feature_detected!("avx"),
+        cfg!(target_feature = "avx")
+    );
+    assert_eq!(
+        is_x86_feature_detected!("avx2"),
+        cfg!(target_feature = "avx2")
+    );
+}
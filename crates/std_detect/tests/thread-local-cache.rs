@@ -0,0 +1,36 @@
+#![cfg(all(
+    feature = "std_detect_thread_local_cache",
+    any(
+        target_arch = "arm",
+        target_arch = "aarch64",
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "powerpc",
+        target_arch = "powerpc64"
+    )
+))]
+#![feature(stdsimd)]
+#![allow(clippy::unwrap_used)]
+
+extern crate std_detect;
+
+use std_detect::detect;
+
+fn features() -> Vec<(&'static str, bool)> {
+    detect::features().collect()
+}
+
+#[test]
+fn thread_local_cache() {
+    // The first read initializes the global cache and this thread's copy:
+    let expected = features();
+    // Steady-state reads are served from this thread's copy:
+    for _ in 0..10_000 {
+        assert_eq!(features(), expected);
+    }
+    // Other threads populate their own copy from the global cache:
+    let threads: Vec<_> = (0..4).map(|_| std::thread::spawn(features)).collect();
+    for t in threads {
+        assert_eq!(t.join().unwrap(), expected);
+    }
+}
@@ -65,6 +65,28 @@ fn dump() {
     println!("xsaves: {:?}", is_x86_feature_detected!("xsaves"));
     println!("xsavec: {:?}", is_x86_feature_detected!("xsavec"));
     println!("cmpxchg16b: {:?}", is_x86_feature_detected!("cmpxchg16b"));
+    println!("lahfsahf: {:?}", is_x86_feature_detected!("lahfsahf"));
+    println!("movbe: {:?}", is_x86_feature_detected!("movbe"));
+    println!("osxsave: {:?}", is_x86_feature_detected!("osxsave"));
+    println!("gfni: {:?}", is_x86_feature_detected!("gfni"));
+    println!("vaes: {:?}", is_x86_feature_detected!("vaes"));
+    println!("vpclmulqdq: {:?}", is_x86_feature_detected!("vpclmulqdq"));
+    println!(
+        "avx5124vnniw: {:?}",
+        is_x86_feature_detected!("avx5124vnniw")
+    );
+    println!(
+        "avx5124fmaps: {:?}",
+        is_x86_feature_detected!("avx5124fmaps")
+    );
+    println!("avx10.1-256: {:?}", is_x86_feature_detected!("avx10.1-256"));
+    println!("avx10.1-512: {:?}", is_x86_feature_detected!("avx10.1-512"));
+    println!("cpb: {:?}", is_x86_feature_detected!("cpb"));
+    println!("rdtscp: {:?}", is_x86_feature_detected!("rdtscp"));
+    println!("x87: {:?}", is_x86_feature_detected!("x87"));
+    println!("clflushopt: {:?}", is_x86_feature_detected!("clflushopt"));
+    println!("clwb: {:?}", is_x86_feature_detected!("clwb"));
+    println!("movrs: {:?}", is_x86_feature_detected!("movrs"));
     println!("adx: {:?}", is_x86_feature_detected!("adx"));
     println!("rtm: {:?}", is_x86_feature_detected!("rtm"));
 }
@@ -156,3 +178,49 @@ fn compare_with_cupid() {
     assert_eq!(is_x86_feature_detected!("adx"), information.adx(),);
     assert_eq!(is_x86_feature_detected!("rtm"), information.rtm(),);
 }
+
+#[test]
+fn rdtscp() {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::{__rdtscp, _rdtsc};
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::{__rdtscp, _rdtsc};
+
+    if !is_x86_feature_detected!("rdtscp") {
+        return;
+    }
+    // `rdtscp` does not fault if it is detected, and reads the same counter
+    // as `rdtsc`:
+    let mut aux = 0;
+    let (before, after) = unsafe { (_rdtsc(), __rdtscp(&mut aux)) };
+    assert!(after >= before, "{} < {}", after, before);
+}
+
+#[test]
+fn require_x86_features() {
+    // `sse` and `sse2` are part of the `x86_64` baseline:
+    #[cfg(target_arch = "x86_64")]
+    assert_eq!(require_x86_features!("sse", "sse2"), Ok(()));
+
+    let result = require_x86_features!("avx2", "fma", "avx512f",);
+    let missing: Vec<_> = [
+        ("avx2", is_x86_feature_detected!("avx2")),
+        ("fma", is_x86_feature_detected!("fma")),
+        ("avx512f", is_x86_feature_detected!("avx512f")),
+    ]
+    .iter()
+    .filter(|&&(_, detected)| !detected)
+    .map(|&(name, _)| name)
+    .collect();
+    match result {
+        Ok(()) => assert!(missing.is_empty()),
+        Err(e) => {
+            println!("{}", e);
+            let mut names: Vec<_> = e.names().collect();
+            names.sort_unstable();
+            let mut missing = missing;
+            missing.sort_unstable();
+            assert_eq!(names, missing);
+        }
+    }
+}